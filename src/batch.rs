@@ -0,0 +1,309 @@
+use crate::config::PROJECT_FILES;
+use crate::format::ndjson::OnError;
+use crate::format::Format;
+use crate::in_place::InPlace;
+use crate::keys_storage::KeysStorage;
+use crate::mask::Masker;
+use crate::{process, Job};
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const GLOB_CHARS: &[char] = &['*', '?', '[', '{'];
+
+/// Checks whether the input names many files: a directory or a glob pattern.
+pub fn is_batch(input: &Path) -> bool {
+    input.is_dir() || (!input.exists() && input.to_string_lossy().contains(GLOB_CHARS))
+}
+
+/// Where the output of every file goes.
+pub enum Target {
+    /// same relative path under the output directory
+    Dir(PathBuf),
+    /// over the input file, keeping a backup with the suffix if it's not empty
+    InPlace(String),
+}
+
+/// Processes every file of a directory or a glob pattern.
+pub struct Batch {
+    pub target: Target,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub format: Option<Format>,
+    pub output_format: Option<Format>,
+    pub preserve_format: bool,
+    pub on_error: OnError,
+}
+
+impl Batch {
+    /// Processes files in parallel, every worker gets its own copy of the masker.
+    /// Tokens and pseudonyms have to be the same in all files, so with a vault or pseudonyms
+    /// files are processed one by one.
+    /// Prints how many values were masked in every file, a failed file doesn't stop the rest.
+    pub fn run(
+        &self,
+        input: &Path,
+        storage: &(impl KeysStorage + Sync),
+        mut masker: Masker,
+    ) -> Result<()> {
+        if let Target::Dir(output_dir) = &self.target {
+            fs::create_dir_all(output_dir)
+                .with_context(|| format!("could not create directory: {}", output_dir.display()))?;
+        }
+        let files = self.find(input)?;
+        if files.is_empty() {
+            bail!("no files to process found in {}", input.display());
+        }
+        let results: Vec<Result<usize>> = match masker.keeps_state() {
+            true => {
+                let results = files
+                    .iter()
+                    .map(|file| self.file(file, storage, &mut masker))
+                    .collect();
+                masker.save()?;
+                results
+            }
+            false => files
+                .par_iter()
+                .map_init(
+                    || masker.worker(),
+                    |masker, file| self.file(file, storage, masker),
+                )
+                .collect(),
+        };
+
+        let mut failed = 0;
+        let mut total = 0;
+        for (file, result) in files.iter().zip(results) {
+            match result {
+                Ok(masked) => {
+                    total += masked;
+                    println!("{}: {} values masked", file.input.display(), masked);
+                }
+                Err(error) => {
+                    failed += 1;
+                    eprintln!("{}: {:#}", file.input.display(), error);
+                }
+            }
+        }
+        println!(
+            "{} files processed, {} values masked",
+            files.len() - failed,
+            total
+        );
+        if failed > 0 {
+            bail!("could not process {} of {} files", failed, files.len());
+        }
+        Ok(())
+    }
+
+    // masks a single file, returns the number of masked values
+    fn file(&self, file: &File, storage: &impl KeysStorage, masker: &mut Masker) -> Result<usize> {
+        let format = Format::resolve(self.format, Some(&file.input));
+        let output_format = self.output_format.unwrap_or(format);
+        let (in_place, output_file) = match &self.target {
+            Target::InPlace(backup_suffix) => {
+                let in_place = InPlace::begin(&file.input, backup_suffix.clone())?;
+                let output_file = in_place.temp_path().to_owned();
+                (Some(in_place), output_file)
+            }
+            Target::Dir(output_dir) => {
+                let mut output_file = output_dir.join(&file.relative);
+                if self.output_format.is_some() {
+                    output_file.set_extension(output_format.extension());
+                }
+                if let Some(parent) = output_file.parent() {
+                    fs::create_dir_all(parent).with_context(|| {
+                        format!("could not create directory: {}", parent.display())
+                    })?;
+                }
+                (None, output_file)
+            }
+        };
+        let job = Job {
+            input_file: Some(file.input.clone()),
+            output_file: Some(output_file),
+            format,
+            output_format,
+            preserve_format: self.preserve_format,
+            on_error: self.on_error,
+        };
+        // drop what is left from a file that failed before
+        masker.take_masked();
+        process(storage, masker, job)?;
+        if let Some(in_place) = in_place {
+            in_place.commit()?;
+        }
+        Ok(masker.take_masked())
+    }
+
+    // walks the base directory of the input honoring `.gitignore`, files are sorted by path
+    fn find(&self, input: &Path) -> Result<Vec<File>> {
+        let (base, pattern) = split_glob(input);
+        let pattern = pattern.map(|pattern| glob(&pattern)).transpose()?;
+        let include = glob_set(&self.include)?;
+        let exclude = glob_set(&self.exclude)?;
+        // output written into the input directory is not an input for the next run
+        let output_dir = match &self.target {
+            Target::Dir(output_dir) => output_dir.canonicalize().ok(),
+            Target::InPlace(_) => None,
+        };
+        let root = match base.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => base.as_path(),
+        };
+
+        let mut files = Vec::new();
+        let walker = WalkBuilder::new(root)
+            .require_git(false)
+            .filter_entry(move |entry| {
+                let is_dir = entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_dir());
+                !is_dir || output_dir.is_none() || entry.path().canonicalize().ok() != output_dir
+            })
+            .build();
+        for entry in walker {
+            let entry = entry?;
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+            // policy files are settings, not data
+            if PROJECT_FILES.iter().any(|name| entry.file_name() == *name) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(root)?.to_owned();
+            let matches = pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(&relative));
+            let included = match self.include.is_empty() {
+                true => Format::from_path(&relative).is_some(),
+                false => include.is_match(&relative),
+            };
+            if matches && included && !exclude.is_match(&relative) {
+                files.push(File {
+                    input: base.join(&relative),
+                    relative,
+                });
+            }
+        }
+        files.sort_by(|a, b| a.relative.cmp(&b.relative));
+        Ok(files)
+    }
+}
+
+struct File {
+    input: PathBuf,
+    // path relative to the base directory of the input, kept under the output directory
+    relative: PathBuf,
+}
+
+// splits the input into the directory to walk and the glob for files under it
+fn split_glob(input: &Path) -> (PathBuf, Option<PathBuf>) {
+    if input.is_dir() {
+        return (input.to_owned(), None);
+    }
+    let mut base = PathBuf::new();
+    let mut pattern = PathBuf::new();
+    for component in input.components() {
+        let is_glob = component.as_os_str().to_string_lossy().contains(GLOB_CHARS);
+        match pattern.as_os_str().is_empty() && !is_glob {
+            true => base.push(component),
+            false => pattern.push(component),
+        }
+    }
+    (base, Some(pattern))
+}
+
+// `*` doesn't cross directories in the input pattern, like in a shell
+fn glob(pattern: &Path) -> Result<GlobMatcher> {
+    let pattern = pattern.to_string_lossy();
+    let glob = GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("invalid glob pattern: {}", pattern))?;
+    Ok(glob.compile_matcher())
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob =
+            Glob::new(pattern).with_context(|| format!("invalid glob pattern: {}", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn batch(include: &[&str], exclude: &[&str]) -> Batch {
+        Batch {
+            target: Target::InPlace(String::new()),
+            include: include.iter().map(|glob| glob.to_string()).collect(),
+            exclude: exclude.iter().map(|glob| glob.to_string()).collect(),
+            format: None,
+            output_format: None,
+            preserve_format: false,
+            on_error: OnError::Abort,
+        }
+    }
+
+    fn relative(files: Vec<File>) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| file.relative.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_glob() {
+        assert_eq!(
+            split_glob(Path::new("fixtures/**/*.json")),
+            (PathBuf::from("fixtures"), Some(PathBuf::from("**/*.json")))
+        );
+        assert_eq!(
+            split_glob(Path::new("*.yaml")),
+            (PathBuf::new(), Some(PathBuf::from("*.yaml")))
+        );
+    }
+
+    #[test]
+    fn test_find_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in [
+            "a.json",
+            "notes.txt",
+            "nested/b.yaml",
+            "nested/deep/c.json",
+            "ignored/d.json",
+        ] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{}").unwrap();
+        }
+        fs::write(dir.path().join(".gitignore"), "ignored/\n").unwrap();
+
+        let all = batch(&[], &[]).find(dir.path()).unwrap();
+        assert_eq!(
+            relative(all),
+            ["a.json", "nested/b.yaml", "nested/deep/c.json"]
+        );
+        let filtered = batch(&["*.json", "*.txt"], &["nested/deep/**"])
+            .find(dir.path())
+            .unwrap();
+        assert_eq!(relative(filtered), ["a.json", "notes.txt"]);
+        let globbed = batch(&[], &[])
+            .find(&dir.path().join("nested/*.yaml"))
+            .unwrap();
+        assert_eq!(relative(globbed), ["b.yaml"]);
+    }
+}
@@ -1,15 +1,399 @@
+use crate::detect::Detector;
+use crate::keys_storage::Preset;
+use crate::mask::pseudonym::PseudonymScope;
+use crate::mask::MaskStrategy;
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Names of project policy files, looked up from the input directory up to the root.
+pub const PROJECT_FILES: [&str; 2] = [".hide.toml", "hide.yaml"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
+    /// literal keys, glob patterns like `*password*` and regexes like `/^api[_-]?key$/i`
+    pub sensitive_keys: Option<HashSet<String>>,
+    /// built-in key sets used together with `sensitive_keys`
+    pub presets: Option<HashSet<Preset>>,
+    /// match keys ignoring case and separators
+    pub normalize_keys: Option<bool>,
+    /// change only masked values in JSON files, keeping the rest byte for byte
+    pub preserve_format: Option<bool>,
+    /// strategy used for keys without their own strategy
+    pub strategy: Option<MaskStrategy>,
+    /// detectors looking for sensitive values under any key
+    pub detectors: Option<HashSet<Detector>>,
+    /// mask only detected parts of the text instead of the whole value
+    pub detect_substrings: Option<bool>,
+    /// minimal Shannon entropy for the high_entropy detector
+    pub entropy_threshold: Option<f64>,
+    /// file with the secret for the hash strategy
+    pub hash_key_file: Option<PathBuf>,
+    /// number of hex characters kept from the hash
+    pub hash_length: Option<usize>,
+    /// text added in front of every hash
+    pub hash_prefix: Option<String>,
+    /// file with the secret for the fpe strategy
+    pub fpe_key_file: Option<PathBuf>,
+    /// seed for the faker strategy, the same seed gives the same fake values
+    pub seed: Option<u64>,
+    /// which values share numbering of the pseudonym strategy: run or key
+    pub pseudonym_scope: Option<PseudonymScope>,
+    /// text in front of the pseudonym number
+    pub pseudonym_prefix: Option<String>,
+    /// file to write pseudonyms with their original values to
+    pub pseudonym_map: Option<PathBuf>,
+    /// vault file for the token strategy
+    pub vault_file: Option<PathBuf>,
+    /// file with the vault secret
+    pub vault_key_file: Option<PathBuf>,
+    /// strategies for specific keys
+    pub key_strategies: Option<HashMap<String, MaskStrategy>>,
+    /// named rule sets selected with `--profile`
+    pub profiles: Option<HashMap<String, Profile>>,
+}
+
+/// Rule set added on top of the global settings, or on top of the profile it inherits.
+/// Keys, presets, detectors and key strategies are added to the inherited ones, other settings
+/// replace them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Profile {
+    /// name of the base profile
+    pub inherits: Option<String>,
+    pub normalize_keys: Option<bool>,
+    pub strategy: Option<MaskStrategy>,
+    pub detect_substrings: Option<bool>,
+    pub entropy_threshold: Option<f64>,
     pub sensitive_keys: Option<HashSet<String>>,
+    pub presets: Option<HashSet<Preset>>,
+    pub detectors: Option<HashSet<Detector>>,
+    pub key_strategies: Option<HashMap<String, MaskStrategy>>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             sensitive_keys: Some(HashSet::new()),
+            presets: None,
+            normalize_keys: None,
+            preserve_format: None,
+            strategy: None,
+            detectors: None,
+            detect_substrings: None,
+            entropy_threshold: None,
+            hash_key_file: None,
+            hash_length: None,
+            hash_prefix: None,
+            fpe_key_file: None,
+            seed: None,
+            pseudonym_scope: None,
+            pseudonym_prefix: None,
+            pseudonym_map: None,
+            vault_file: None,
+            vault_key_file: None,
+            key_strategies: None,
+            profiles: None,
+        }
+    }
+}
+
+/// Finds the project policy file for the input: in its directory or the closest parent
+/// directory having one. Input from stdin starts from the current directory.
+pub fn discover(input: Option<&Path>) -> Option<PathBuf> {
+    let start = input
+        .and_then(|input| input.ancestors().find(|dir| dir.is_dir()))
+        .unwrap_or(Path::new("."));
+    let start = start.canonicalize().ok()?;
+    start
+        .ancestors()
+        .flat_map(|dir| PROJECT_FILES.map(|name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Reads a policy file, YAML if it has `.yaml` or `.yml` extension and TOML otherwise.
+/// Relative paths in it are relative to the directory of the file.
+pub fn read(path: &Path) -> Result<Config> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("could not read config file: {}", path.display()))?;
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let config: Config = match extension {
+        Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(anyhow::Error::from),
+        _ => toml::from_str(&text).map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("could not parse config file: {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let relative_to_file = |file: Option<PathBuf>| file.map(|file| dir.join(file));
+    Ok(Config {
+        hash_key_file: relative_to_file(config.hash_key_file),
+        fpe_key_file: relative_to_file(config.fpe_key_file),
+        pseudonym_map: relative_to_file(config.pseudonym_map),
+        vault_file: relative_to_file(config.vault_file),
+        vault_key_file: relative_to_file(config.vault_key_file),
+        ..config
+    })
+}
+
+impl Config {
+    /// Puts `other` on top of these settings: keys and detectors of both are used,
+    /// key strategies and profiles of `other` win, and so do its other settings when set.
+    pub fn merge(self, other: Config) -> Config {
+        let mut profiles = self.profiles.unwrap_or_default();
+        for (name, profile) in other.profiles.unwrap_or_default() {
+            let merged = match profiles.remove(&name) {
+                Some(base) => base.merge(profile),
+                None => profile,
+            };
+            profiles.insert(name, merged);
+        }
+        Config {
+            sensitive_keys: union(self.sensitive_keys, other.sensitive_keys),
+            presets: union(self.presets, other.presets),
+            normalize_keys: other.normalize_keys.or(self.normalize_keys),
+            preserve_format: other.preserve_format.or(self.preserve_format),
+            strategy: other.strategy.or(self.strategy),
+            detectors: union(self.detectors, other.detectors),
+            detect_substrings: other.detect_substrings.or(self.detect_substrings),
+            entropy_threshold: other.entropy_threshold.or(self.entropy_threshold),
+            hash_key_file: other.hash_key_file.or(self.hash_key_file),
+            hash_length: other.hash_length.or(self.hash_length),
+            hash_prefix: other.hash_prefix.or(self.hash_prefix),
+            fpe_key_file: other.fpe_key_file.or(self.fpe_key_file),
+            seed: other.seed.or(self.seed),
+            pseudonym_scope: other.pseudonym_scope.or(self.pseudonym_scope),
+            pseudonym_prefix: other.pseudonym_prefix.or(self.pseudonym_prefix),
+            pseudonym_map: other.pseudonym_map.or(self.pseudonym_map),
+            vault_file: other.vault_file.or(self.vault_file),
+            vault_key_file: other.vault_key_file.or(self.vault_key_file),
+            key_strategies: union(self.key_strategies, other.key_strategies),
+            profiles: (!profiles.is_empty()).then_some(profiles),
+        }
+    }
+
+    /// Returns global settings with the profile and all its base profiles applied.
+    pub fn with_profile(&self, name: &str) -> Result<Config> {
+        let mut chain = vec![self.profile(name)?];
+        while let Some(base) = &chain[chain.len() - 1].inherits {
+            let profile = self.profile(base)?;
+            if chain
+                .iter()
+                .any(|inherited| std::ptr::eq(*inherited, profile))
+            {
+                bail!("profile '{}' inherits itself", base);
+            }
+            chain.push(profile);
+        }
+        let mut config = self.clone();
+        for profile in chain.into_iter().rev() {
+            profile.apply_to(&mut config);
+        }
+        Ok(config)
+    }
+
+    /// Keys of the profile itself, without inherited ones, or global keys without a profile.
+    pub fn own_keys(&self, profile: Option<&str>) -> HashSet<String> {
+        let keys = match profile {
+            Some(name) => self
+                .profiles
+                .as_ref()
+                .and_then(|profiles| profiles.get(name))
+                .and_then(|profile| profile.sensitive_keys.clone()),
+            None => self.sensitive_keys.clone(),
+        };
+        keys.unwrap_or_default()
+    }
+
+    /// Replaces keys of the profile, creating it if it doesn't exist yet.
+    pub fn set_own_keys(&mut self, profile: Option<&str>, keys: HashSet<String>) {
+        match profile {
+            Some(name) => {
+                let profiles = self.profiles.get_or_insert_with(HashMap::new);
+                profiles.entry(name.to_owned()).or_default().sensitive_keys = Some(keys);
+            }
+            None => self.sensitive_keys = Some(keys),
+        }
+    }
+
+    fn profile(&self, name: &str) -> Result<&Profile> {
+        let profile = self
+            .profiles
+            .as_ref()
+            .and_then(|profiles| profiles.get(name));
+        match profile {
+            Some(profile) => Ok(profile),
+            None => bail!("unknown profile '{}'", name),
+        }
+    }
+}
+
+impl Profile {
+    fn merge(self, other: Profile) -> Profile {
+        Profile {
+            inherits: other.inherits.or(self.inherits),
+            normalize_keys: other.normalize_keys.or(self.normalize_keys),
+            strategy: other.strategy.or(self.strategy),
+            detect_substrings: other.detect_substrings.or(self.detect_substrings),
+            entropy_threshold: other.entropy_threshold.or(self.entropy_threshold),
+            sensitive_keys: union(self.sensitive_keys, other.sensitive_keys),
+            presets: union(self.presets, other.presets),
+            detectors: union(self.detectors, other.detectors),
+            key_strategies: union(self.key_strategies, other.key_strategies),
         }
     }
+
+    fn apply_to(&self, config: &mut Config) {
+        if let Some(keys) = &self.sensitive_keys {
+            config
+                .sensitive_keys
+                .get_or_insert_with(HashSet::new)
+                .extend(keys.iter().cloned());
+        }
+        if let Some(presets) = &self.presets {
+            config
+                .presets
+                .get_or_insert_with(HashSet::new)
+                .extend(presets);
+        }
+        if let Some(detectors) = &self.detectors {
+            config
+                .detectors
+                .get_or_insert_with(HashSet::new)
+                .extend(detectors);
+        }
+        if let Some(key_strategies) = &self.key_strategies {
+            config
+                .key_strategies
+                .get_or_insert_with(HashMap::new)
+                .extend(key_strategies.clone());
+        }
+        config.normalize_keys = self.normalize_keys.or(config.normalize_keys);
+        config.strategy = self.strategy.clone().or(config.strategy.take());
+        config.detect_substrings = self.detect_substrings.or(config.detect_substrings);
+        config.entropy_threshold = self.entropy_threshold.or(config.entropy_threshold);
+    }
+}
+
+// entries of both collections, for maps the ones from `other` win
+fn union<T: Extend<I> + IntoIterator<Item = I>, I>(base: Option<T>, other: Option<T>) -> Option<T> {
+    match (base, other) {
+        (Some(mut base), Some(other)) => {
+            base.extend(other);
+            Some(base)
+        }
+        (base, other) => base.or(other),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profile(inherits: Option<&str>, keys: &[&str]) -> Profile {
+        Profile {
+            inherits: inherits.map(str::to_owned),
+            sensitive_keys: Some(keys.iter().map(|key| key.to_string()).collect()),
+            ..Profile::default()
+        }
+    }
+
+    fn config(profiles: Vec<(&str, Profile)>) -> Config {
+        Config {
+            sensitive_keys: Some(HashSet::from([String::from("password")])),
+            strategy: Some(MaskStrategy::Null),
+            profiles: Some(
+                profiles
+                    .into_iter()
+                    .map(|(name, profile)| (name.to_owned(), profile))
+                    .collect(),
+            ),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_profile_inherits_base() {
+        let mut logs = profile(Some("base"), &["ip"]);
+        logs.strategy = Some(MaskStrategy::Remove);
+        let config = config(vec![("base", profile(None, &["email"])), ("logs", logs)]);
+
+        let resolved = config.with_profile("logs").unwrap();
+        let keys = resolved.sensitive_keys.unwrap();
+        let expected = ["password", "email", "ip"].map(String::from);
+        assert_eq!(keys, HashSet::from(expected));
+        assert_eq!(resolved.strategy, Some(MaskStrategy::Remove));
+        assert_eq!(
+            config.with_profile("base").unwrap().strategy,
+            Some(MaskStrategy::Null)
+        );
+        assert_eq!(
+            config.own_keys(Some("logs")),
+            HashSet::from([String::from("ip")])
+        );
+    }
+
+    #[test]
+    fn test_merge_project_over_user_config() {
+        let user = config(vec![("logs", profile(None, &["ip"]))]);
+        let project = Config {
+            sensitive_keys: Some(HashSet::from([String::from("email")])),
+            strategy: Some(MaskStrategy::Remove),
+            profiles: Some(HashMap::from([(
+                String::from("logs"),
+                profile(None, &["user_agent"]),
+            )])),
+            ..Config::default()
+        };
+
+        let merged = user.merge(project);
+        let keys = HashSet::from(["password", "email"].map(String::from));
+        assert_eq!(merged.sensitive_keys, Some(keys));
+        assert_eq!(merged.strategy, Some(MaskStrategy::Remove));
+        let keys = HashSet::from(["ip", "user_agent"].map(String::from));
+        assert_eq!(merged.own_keys(Some("logs")), keys);
+    }
+
+    #[test]
+    fn test_discover_and_read_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("fixtures/users");
+        fs::create_dir_all(&nested).unwrap();
+        let input = nested.join("users.json");
+        assert_eq!(discover(Some(&input)), None);
+
+        let policy = dir.path().join("hide.yaml");
+        fs::write(
+            &policy,
+            "sensitive_keys: [email]
+vault_file: secrets/vault
+",
+        )
+        .unwrap();
+        let found = discover(Some(&input)).unwrap();
+        assert_eq!(found, policy.canonicalize().unwrap());
+
+        let config = read(&found).unwrap();
+        assert_eq!(
+            config.sensitive_keys,
+            Some(HashSet::from([String::from("email")]))
+        );
+        assert_eq!(
+            config.vault_file,
+            Some(found.with_file_name("secrets/vault"))
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_cyclic_profiles() {
+        let config = config(vec![
+            ("a", profile(Some("b"), &[])),
+            ("b", profile(Some("a"), &[])),
+            ("c", profile(Some("missing"), &[])),
+        ]);
+        let error = config.with_profile("a").unwrap_err().to_string();
+        assert!(error.contains("inherits itself"), "{}", error);
+        let error = config.with_profile("c").unwrap_err().to_string();
+        assert_eq!(error, "unknown profile 'missing'");
+        assert!(config.with_profile("x").is_err());
+    }
 }
@@ -0,0 +1,176 @@
+use regex::Regex;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::Range;
+use std::sync::LazyLock;
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,4}\)|\d{2,4})[\s.-]?\d{3,4}[\s.-]?\d{3,4}").unwrap()
+});
+static CREDIT_CARD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap());
+static IBAN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]){11,30}\b").unwrap());
+static IPV4: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap());
+static IPV6: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:[0-9A-Fa-f]{0,4}:){2,7}(?:(?:\d{1,3}\.){3}\d{1,3}|[0-9A-Fa-f]{0,4})").unwrap()
+});
+static SSN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap());
+
+pub fn emails(text: &str) -> Vec<Range<usize>> {
+    EMAIL.find_iter(text).map(|m| m.range()).collect()
+}
+
+pub fn phones(text: &str) -> Vec<Range<usize>> {
+    PHONE
+        .find_iter(text)
+        .filter(|m| {
+            let digits = m.as_str().chars().filter(char::is_ascii_digit).count();
+            (10..=15).contains(&digits) || (m.as_str().starts_with('+') && digits >= 7)
+        })
+        .map(|m| m.range())
+        .collect()
+}
+
+pub fn credit_cards(text: &str) -> Vec<Range<usize>> {
+    CREDIT_CARD
+        .find_iter(text)
+        .filter(|m| {
+            let digits: Vec<u32> = m.as_str().chars().filter_map(|c| c.to_digit(10)).collect();
+            (13..=19).contains(&digits.len()) && luhn(&digits)
+        })
+        .map(|m| m.range())
+        .collect()
+}
+
+pub fn ibans(text: &str) -> Vec<Range<usize>> {
+    IBAN.find_iter(text)
+        .filter_map(|m| {
+            // the match can swallow uppercase words after the IBAN, so drop them one by one
+            let candidate = m.as_str();
+            let mut ends: Vec<usize> = candidate.match_indices(' ').map(|(i, _)| i).collect();
+            ends.push(candidate.len());
+            ends.into_iter()
+                .rev()
+                .find(|&end| iban_checksum(&candidate[..end]))
+                .map(|end| m.start()..m.start() + end)
+        })
+        .collect()
+}
+
+pub fn ipv4s(text: &str) -> Vec<Range<usize>> {
+    IPV4.find_iter(text)
+        .filter(|m| m.as_str().parse::<Ipv4Addr>().is_ok())
+        .map(|m| m.range())
+        .collect()
+}
+
+pub fn ipv6s(text: &str) -> Vec<Range<usize>> {
+    IPV6.find_iter(text)
+        .filter(|m| m.as_str().parse::<Ipv6Addr>().is_ok())
+        .map(|m| m.range())
+        .collect()
+}
+
+pub fn ssns(text: &str) -> Vec<Range<usize>> {
+    SSN.find_iter(text)
+        .filter(|m| {
+            let parts: Vec<&str> = m.as_str().split('-').collect();
+            let area: u32 = parts[0].parse().unwrap_or(0);
+            // numbers never assigned by Social Security Administration
+            area != 0 && area != 666 && area < 900 && parts[1] != "00" && parts[2] != "0000"
+        })
+        .map(|m| m.range())
+        .collect()
+}
+
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            1 if d * 2 > 9 => d * 2 - 9,
+            1 => d * 2,
+            _ => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn iban_checksum(iban: &str) -> bool {
+    let iban: String = iban.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+    // move country code and check digits to the end, letters become numbers: A = 10, B = 11...
+    let rearranged = iban[4..].chars().chain(iban[..4].chars());
+    let mut remainder = 0u32;
+    for c in rearranged {
+        let Some(value) = c.to_digit(36) else {
+            return false;
+        };
+        remainder = match value {
+            0..=9 => (remainder * 10 + value) % 97,
+            _ => (remainder * 100 + value) % 97,
+        };
+    }
+    remainder == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn found(ranges: Vec<Range<usize>>, text: &str) -> Vec<String> {
+        ranges.into_iter().map(|r| text[r].to_owned()).collect()
+    }
+
+    #[test]
+    fn test_emails() {
+        let text = "write to john.doe+news@example.co.uk or admin@localhost";
+        assert_eq!(
+            found(emails(text), text),
+            vec!["john.doe+news@example.co.uk"]
+        );
+    }
+
+    #[test]
+    fn test_phones() {
+        let text = "call +1 (555) 123-4567 or 0441234567, not 2023-10-18";
+        assert_eq!(
+            found(phones(text), text),
+            vec!["+1 (555) 123-4567", "0441234567"]
+        );
+    }
+
+    #[test]
+    fn test_credit_cards_are_luhn_validated() {
+        let text = "card 4111 1111 1111 1111, not 4111 1111 1111 1112";
+        assert_eq!(found(credit_cards(text), text), vec!["4111 1111 1111 1111"]);
+    }
+
+    #[test]
+    fn test_ibans() {
+        let text = "iban GB82 WEST 1234 5698 7654 32 and DE89370400440532013000 BIC COBADEFFXXX, not GB00WEST12345698765432";
+        assert_eq!(
+            found(ibans(text), text),
+            vec!["GB82 WEST 1234 5698 7654 32", "DE89370400440532013000"]
+        );
+    }
+
+    #[test]
+    fn test_ip_addresses() {
+        let text = "from 192.168.0.1 and 2001:db8::ff00:42:8329, not 999.1.1.1 or 12:30:45";
+        assert_eq!(found(ipv4s(text), text), vec!["192.168.0.1"]);
+        assert_eq!(found(ipv6s(text), text), vec!["2001:db8::ff00:42:8329"]);
+    }
+
+    #[test]
+    fn test_ssns() {
+        let text = "ssn 123-45-6789, not 000-12-3456 or 666-12-3456";
+        assert_eq!(found(ssns(text), text), vec!["123-45-6789"]);
+    }
+}
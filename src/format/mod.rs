@@ -0,0 +1,196 @@
+use anyhow::{bail, Context, Result};
+use ndjson::OnError;
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+mod json;
+pub mod ndjson;
+mod toml;
+mod yaml;
+
+/// File format of the input and output, written as `json`, `yaml`, `toml` or `ndjson`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+    /// one JSON record per line, also known as JSON Lines
+    Ndjson,
+}
+
+/// Parsed input file.
+pub struct Input {
+    pub format: Format,
+    /// original text, used as a reference when writing output in the same format
+    pub text: String,
+    pub documents: Vec<Value>,
+}
+
+impl Format {
+    /// Guesses format by the file extension, `None` for unknown extensions.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// Extension used for files written in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Ndjson => "ndjson",
+        }
+    }
+
+    /// Returns the given format, or the one guessed by the file extension, JSON otherwise.
+    /// Input from stdin has no extension, so it's JSON unless the format is given.
+    pub fn resolve(format: Option<Format>, path: Option<&Path>) -> Format {
+        format
+            .or_else(|| path.and_then(Format::from_path))
+            .unwrap_or_default()
+    }
+
+    /// Parses all documents of the text, YAML streams can contain more than one.
+    /// Invalid NDJSON lines are handled by the policy.
+    pub fn parse(&self, text: &str, on_error: OnError) -> Result<Vec<Value>> {
+        match self {
+            Format::Json => Ok(vec![json::parse(text)?]),
+            Format::Yaml => yaml::parse(text),
+            Format::Toml => Ok(vec![self::toml::parse(text)?]),
+            Format::Ndjson => ndjson::parse(text, on_error),
+        }
+    }
+
+    /// Writes documents in this format. `source` is the original text in the same format,
+    /// it helps to keep types that JSON doesn't have, like TOML datetimes.
+    pub fn write(&self, documents: &[Value], source: Option<&str>) -> Result<String> {
+        match self {
+            // JSON has one document per file, so more of them are written as an array
+            Format::Json => match documents {
+                [document] => Ok(serde_json::to_string_pretty(document)?),
+                _ => Ok(serde_json::to_string_pretty(documents)?),
+            },
+            Format::Yaml => yaml::write(documents),
+            Format::Toml => match documents {
+                [document] => self::toml::write(document, source),
+                _ => bail!("TOML supports only one document"),
+            },
+            Format::Ndjson => ndjson::write(documents),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            _ => Err(format!(
+                "unknown format '{}', expected one of: json, yaml, toml, ndjson",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Ndjson => "ndjson",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Reads the whole file, or stdin if there is no path, in the given format.
+pub fn read_input(path: Option<&Path>, format: Format, on_error: OnError) -> Result<Input> {
+    let text = match path {
+        Some(path) => std::fs::read_to_string(path),
+        None => io::read_to_string(io::stdin()),
+    }
+    .with_context(|| format!("could not read {}", describe(path)))?;
+    let documents = format
+        .parse(&text, on_error)
+        .with_context(|| format!("could not parse {}", describe(path)))?;
+    Ok(Input {
+        format,
+        text,
+        documents,
+    })
+}
+
+/// Names the input in messages: `file: <path>` or `stdin`.
+pub fn describe(path: Option<&Path>) -> String {
+    match path {
+        Some(path) => format!("file: {}", path.display()),
+        None => String::from("stdin"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Format::from_path(Path::new("a.json")), Some(Format::Json));
+        assert_eq!(Format::from_path(Path::new("a.YML")), Some(Format::Yaml));
+        assert_eq!(Format::from_path(Path::new("a.yaml")), Some(Format::Yaml));
+        assert_eq!(Format::from_path(Path::new("a.toml")), Some(Format::Toml));
+        assert_eq!(
+            Format::from_path(Path::new("a.jsonl")),
+            Some(Format::Ndjson)
+        );
+        assert_eq!(Format::from_path(Path::new("a.txt")), None);
+        assert_eq!(Format::from_path(Path::new("a")), None);
+    }
+
+    #[test]
+    fn test_toml_single_document() {
+        let documents = Format::Yaml
+            .parse("a: 1\n---\na: 2\n", OnError::Abort)
+            .unwrap();
+        assert!(Format::Toml.write(&documents, None).is_err());
+    }
+
+    #[test]
+    fn test_json_documents_as_array() {
+        let documents = Format::Yaml
+            .parse("a: 1\n---\na: 2\n", OnError::Abort)
+            .unwrap();
+        let json = Format::Json.write(&documents, None).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&json).unwrap(),
+            serde_json::json!([{"a": 1}, {"a": 2}])
+        );
+        let json = Format::Json.write(&documents[..1], None).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&json).unwrap(),
+            serde_json::json!({"a": 1})
+        );
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!("xml".parse::<Format>().is_err());
+        assert_eq!("YAML".parse::<Format>(), Ok(Format::Yaml));
+    }
+}
@@ -0,0 +1,180 @@
+use super::json;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+/// What to do with a line that is not valid JSON, written as `skip`, `pass` or `abort`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// drop the line from the output
+    Skip,
+    /// write the line to the output as is, without masking
+    Pass,
+    /// stop with an error
+    #[default]
+    Abort,
+}
+
+/// Parses every non-empty line as a separate document, lines that are not valid JSON
+/// are handled by the policy. They can't be passed as is, as the whole file is converted
+/// to another format.
+pub fn parse(text: &str, on_error: OnError) -> Result<Vec<Value>> {
+    let mut documents = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let number = index + 1;
+        match (json::parse(line), on_error) {
+            (Ok(document), _) => documents.push(document),
+            (Err(error), OnError::Skip) => log::warn!("skipped line {}: {}", number, error),
+            (Err(error), OnError::Pass) => bail!(
+                "invalid line {}: {}, it can't be passed as is to another format",
+                number,
+                error
+            ),
+            (Err(error), OnError::Abort) => {
+                return Err(error).with_context(|| format!("invalid line {}", number))
+            }
+        }
+    }
+    Ok(documents)
+}
+
+pub fn write(documents: &[Value]) -> Result<String> {
+    let lines = documents
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(lines.join("\n"))
+}
+
+/// Reads records line by line and writes every transformed record right away,
+/// so only one line is kept in memory at a time.
+pub fn stream(
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    on_error: OnError,
+    mut transform: impl FnMut(&Value) -> Value,
+) -> Result<()> {
+    let mut line = Vec::new();
+    let mut number = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        number += 1;
+        let record = match std::str::from_utf8(&line) {
+            Ok(text) if text.trim().is_empty() => continue,
+            Ok(text) => json::parse(text).map_err(anyhow::Error::from),
+            Err(error) => Err(error.into()),
+        };
+        match (record, on_error) {
+            (Ok(record), _) => {
+                let mut record = serde_json::to_vec(&transform(&record))?;
+                record.push(b'\n');
+                writer.write_all(&record)?;
+            }
+            (Err(error), OnError::Skip) => log::warn!("skipped line {}: {}", number, error),
+            (Err(error), OnError::Pass) => {
+                log::warn!("passed line {} as is: {}", number, error);
+                writer.write_all(&line)?;
+                if !line.ends_with(b"\n") {
+                    writer.write_all(b"\n")?;
+                }
+            }
+            (Err(error), OnError::Abort) => bail!("could not parse line {}: {}", number, error),
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+impl FromStr for OnError {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(OnError::Skip),
+            "pass" => Ok(OnError::Pass),
+            "abort" => Ok(OnError::Abort),
+            _ => Err(format!(
+                "unknown policy '{}', expected one of: skip, pass, abort",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            OnError::Skip => "skip",
+            OnError::Pass => "pass",
+            OnError::Abort => "abort",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    const INPUT: &str = "{\"name\":\"alice\"}\nnot json\n\n{\"name\":\"bob\"}";
+
+    fn run(on_error: OnError) -> Result<String> {
+        let mut output = Vec::new();
+        stream(
+            INPUT.as_bytes(),
+            &mut output,
+            on_error,
+            |_| json!({"name": "x"}),
+        )?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_stream_skip() {
+        assert_eq!(
+            run(OnError::Skip).unwrap(),
+            "{\"name\":\"x\"}\n{\"name\":\"x\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_stream_pass() {
+        assert_eq!(
+            run(OnError::Pass).unwrap(),
+            "{\"name\":\"x\"}\nnot json\n{\"name\":\"x\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_stream_abort() {
+        let error = run(OnError::Abort).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_parse_and_write() {
+        let documents = parse("{\"a\":1}\n\n[2]\n", OnError::Abort).unwrap();
+        assert_eq!(documents, vec![json!({"a": 1}), json!([2])]);
+        assert_eq!(write(&documents).unwrap(), "{\"a\":1}\n[2]");
+        assert!(parse("{\"a\":1}\n{", OnError::Abort).is_err());
+    }
+
+    #[test]
+    fn test_parse_with_policy() {
+        assert_eq!(
+            parse(INPUT, OnError::Skip).unwrap(),
+            vec![json!({"name": "alice"}), json!({"name": "bob"})]
+        );
+        let error = parse(INPUT, OnError::Pass).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{bail, Result};
+use serde_json::{Map, Number, Value};
+use toml::value::Datetime;
+use toml::Table;
+
+/// Parses TOML document, datetimes become strings like `1979-05-27T07:32:00Z`.
+pub fn parse(text: &str) -> Result<Value> {
+    let table: Table = text.parse()?;
+    Ok(table_to_json(table))
+}
+
+/// Writes the document as TOML. Strings are written back as datetimes where the source
+/// document had a datetime and the value still is one, so unmasked datetimes keep their type.
+/// TOML has no null, so null values are dropped.
+pub fn write(document: &Value, source: Option<&str>) -> Result<String> {
+    let Value::Object(map) = document else {
+        bail!("TOML document must be a table");
+    };
+    let source = source.and_then(|source| source.parse::<Table>().ok());
+    let table = map_to_toml(map, source.as_ref());
+    Ok(toml::to_string(&table)?.trim_end().to_owned())
+}
+
+fn to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(string) => Value::String(string),
+        toml::Value::Integer(integer) => Value::from(integer),
+        toml::Value::Float(float) => Number::from_f64(float).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(bool) => Value::Bool(bool),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => Value::Array(array.into_iter().map(to_json).collect()),
+        toml::Value::Table(table) => table_to_json(table),
+    }
+}
+
+fn table_to_json(table: Table) -> Value {
+    Value::Object(
+        table
+            .into_iter()
+            .map(|(key, value)| (key, to_json(value)))
+            .collect(),
+    )
+}
+
+fn to_toml(value: &Value, source: Option<&toml::Value>) -> Option<toml::Value> {
+    let value = match value {
+        Value::Null => return None,
+        Value::Bool(bool) => toml::Value::Boolean(*bool),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => toml::Value::Integer(integer),
+            None => toml::Value::Float(number.as_f64()?),
+        },
+        Value::String(string) => match (source, string.parse::<Datetime>()) {
+            (Some(toml::Value::Datetime(_)), Ok(datetime)) => toml::Value::Datetime(datetime),
+            _ => toml::Value::String(string.to_owned()),
+        },
+        Value::Array(array) => {
+            let source = source.and_then(toml::Value::as_array);
+            toml::Value::Array(
+                array
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, item)| to_toml(item, source.and_then(|s| s.get(i))))
+                    .collect(),
+            )
+        }
+        Value::Object(map) => {
+            toml::Value::Table(map_to_toml(map, source.and_then(toml::Value::as_table)))
+        }
+    };
+    Some(value)
+}
+
+fn map_to_toml(map: &Map<String, Value>, source: Option<&Table>) -> Table {
+    map.iter()
+        .filter_map(|(key, value)| {
+            let source = source.and_then(|source| source.get(key));
+            to_toml(value, source).map(|value| (key.to_owned(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    const SOURCE: &str = r#"
+created = 1979-05-27T07:32:00Z
+note = "1979-05-27"
+
+[[servers]]
+host = "alpha"
+password = "secret"
+
+[[servers]]
+host = "beta"
+password = "hunter2"
+"#;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(SOURCE).unwrap(),
+            json!({
+                "created": "1979-05-27T07:32:00Z",
+                "note": "1979-05-27",
+                "servers": [
+                    {"host": "alpha", "password": "secret"},
+                    {"host": "beta", "password": "hunter2"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_keeps_datetimes_and_tables() {
+        let document = parse(SOURCE).unwrap();
+        let output = write(&document, Some(SOURCE)).unwrap();
+        assert_eq!(
+            output.parse::<Table>().unwrap(),
+            SOURCE.parse::<Table>().unwrap()
+        );
+        assert!(output.contains("created = 1979-05-27T07:32:00Z"));
+        assert!(output.contains("note = \"1979-05-27\""));
+    }
+
+    #[test]
+    fn test_write_masked_datetime_as_string() {
+        let document = json!({"created": "String"});
+        let output = write(&document, Some(SOURCE)).unwrap();
+        assert_eq!(output, "created = \"String\"");
+    }
+
+    #[test]
+    fn test_write_drops_nulls() {
+        let document = json!({"password": null, "list": [1, null, 2]});
+        assert_eq!(write(&document, None).unwrap(), "list = [1, 2]");
+    }
+
+    #[test]
+    fn test_write_requires_table() {
+        assert!(write(&json!([1, 2]), None).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+
+/// Parses every document of the YAML stream.
+pub fn parse(text: &str) -> Result<Vec<Value>> {
+    serde_yaml::Deserializer::from_str(text)
+        .map(|document| {
+            let value = serde_yaml::Value::deserialize(document)?;
+            to_json(value)
+        })
+        .collect()
+}
+
+pub fn write(documents: &[Value]) -> Result<String> {
+    let documents = documents
+        .iter()
+        .map(serde_yaml::to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    // serde_yaml ends every document with a new line already
+    Ok(documents.join("---\n").trim_end().to_owned())
+}
+
+fn to_json(value: serde_yaml::Value) -> Result<Value> {
+    let value = match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(bool) => Value::Bool(bool),
+        serde_yaml::Value::Number(number) => number_to_json(&number),
+        serde_yaml::Value::String(string) => Value::String(string),
+        serde_yaml::Value::Sequence(sequence) => {
+            Value::Array(sequence.into_iter().map(to_json).collect::<Result<_>>()?)
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut map = Map::with_capacity(mapping.len());
+            for (key, value) in mapping {
+                map.insert(key_to_json(key)?, to_json(value)?);
+            }
+            Value::Object(map)
+        }
+        // tags like `!secret` only describe the value, keep the value itself
+        serde_yaml::Value::Tagged(tagged) => to_json(tagged.value)?,
+    };
+    Ok(value)
+}
+
+fn number_to_json(number: &serde_yaml::Number) -> Value {
+    if let Some(number) = number.as_u64() {
+        return Value::from(number);
+    }
+    if let Some(number) = number.as_i64() {
+        return Value::from(number);
+    }
+    // .nan and .inf have no JSON representation
+    number
+        .as_f64()
+        .and_then(Number::from_f64)
+        .map_or(Value::Null, Value::Number)
+}
+
+// JSON keys are always strings, so `200:` or `true:` keys become "200" and "true"
+fn key_to_json(key: serde_yaml::Value) -> Result<String> {
+    match key {
+        serde_yaml::Value::String(key) => Ok(key),
+        serde_yaml::Value::Null => Ok(String::from("null")),
+        serde_yaml::Value::Bool(key) => Ok(key.to_string()),
+        serde_yaml::Value::Number(key) => Ok(key.to_string()),
+        serde_yaml::Value::Tagged(tagged) => key_to_json(tagged.value),
+        _ => bail!("complex mapping keys are not supported"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_documents() {
+        let text = "name: alice\nage: 30\n---\n- 1\n- two\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            vec![json!({"name": "alice", "age": 30}), json!([1, "two"])]
+        );
+    }
+
+    #[test]
+    fn test_parse_non_string_keys_and_tags() {
+        let text = "200: ok\ntrue: yes\npassword: !secret hunter2\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            vec![json!({"200": "ok", "true": "yes", "password": "hunter2"})]
+        );
+    }
+
+    #[test]
+    fn test_write_documents() {
+        let documents = vec![json!({"name": "alice"}), json!({"name": "bob"})];
+        assert_eq!(write(&documents).unwrap(), "name: alice\n---\nname: bob");
+    }
+}
@@ -1,28 +1,212 @@
-use clap::Parser;
+use crate::detect::Detector;
+use crate::format::ndjson::OnError;
+use crate::format::Format;
+use crate::keys_storage::{KeysFormat, Preset};
+use crate::mask::pseudonym::PseudonymScope;
+use crate::mask::MaskStrategy;
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
-#[command(author, version, about, long_about = None, arg_required_else_help = true)]
+#[command(author, version, about, long_about = None)]
 #[group(multiple = true)]
 pub struct HideArgs {
-    /// path to the input JSON file
+    #[command(subcommand)]
+    pub command: Option<HideCommand>,
+    /// path to the input JSON, YAML, TOML or NDJSON file, a directory or a glob pattern,
+    /// stdin if not given or `-`
     #[arg(short = 'i', long = "input", value_name = "FILE")]
     pub input_file: Option<PathBuf>,
-    /// path to the output file, requires input_file
+    /// path to the output file, stdout if not given or `-`
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    pub output_file: Option<PathBuf>,
+    /// directory for the output of a directory or glob input, keeping the structure of the input
+    #[arg(long = "output-dir", value_name = "DIR", conflicts_with_all = ["output_file", "in_place"])]
+    pub output_dir: Option<PathBuf>,
+    /// process only files of a directory or glob input matching the glob, can be repeated
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// skip files of a directory or glob input matching the glob, can be repeated
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// overwrite the input file with the output, keeping a backup with SUFFIX if given
     #[arg(
-        short = 'o',
-        long = "output",
-        value_name = "FILE",
-        requires = "input_file"
+        long = "in-place",
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        conflicts_with = "output_file"
     )]
-    pub output_file: Option<PathBuf>,
+    pub in_place: Option<String>,
+    /// input format: json, yaml, toml or ndjson, guessed by the file extension by default
+    #[arg(long = "format", value_name = "FORMAT")]
+    pub format: Option<Format>,
+    /// output format: json, yaml, toml or ndjson, same as the input by default
+    #[arg(long = "output-format", value_name = "FORMAT")]
+    pub output_format: Option<Format>,
+    /// what to do with malformed NDJSON lines: skip, pass or abort
+    #[arg(long = "on-error", value_name = "POLICY", default_value_t = OnError::Abort)]
+    pub on_error: OnError,
+    /// change only masked values, keeping whitespace, comments, key order and numbers of JSON as is
+    #[arg(long = "preserve-format")]
+    pub preserve_format: bool,
     /// enable debug mode
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub debug: bool,
+    /// policy file to use instead of `.hide.toml` or `hide.yaml` found next to the input
+    #[arg(long = "config", value_name = "FILE", global = true)]
+    pub config_file: Option<PathBuf>,
+    /// ignore the user config file, use only the policy file
+    #[arg(long = "no-user-config", global = true)]
+    pub no_user_config: bool,
+    /// profile from the config file to use, keys are added to and removed from it
+    #[arg(short = 'p', long = "profile", value_name = "NAME", global = true)]
+    pub profile: Option<String>,
     /// add keys to hide in the JSON
     #[arg(long = "add-keys", value_delimiter = ',')]
     pub add_keys: Vec<String>,
     /// remove keys from hiding in the JSON
     #[arg(long = "remove-keys", value_delimiter = ',')]
     pub remove_keys: Vec<String>,
+    /// built-in keys to hide for this run: pii-basic, credentials, payment or health
+    #[arg(long = "preset", value_name = "PRESETS", value_delimiter = ',')]
+    pub presets: Vec<Preset>,
+    /// match keys ignoring case and separators, so phone_number also hides phoneNumber and phone-number
+    #[arg(long = "normalize-keys")]
+    pub normalize_keys: bool,
+    /// how to hide values for this run: placeholder, null, remove, partial, hash, token, fpe,
+    /// fake, faker, pseudonym or literal:<text>; partial takes options like partial:first=2,last=2,char=#
+    /// or partial:email; numbers can be generalized with bucket:<size>, round:<step>,
+    /// truncate:<decimals>, noise:<bound> or laplace:<epsilon>[,<sensitivity>]
+    #[arg(short = 's', long = "strategy", value_name = "STRATEGY")]
+    pub strategy: Option<MaskStrategy>,
+    /// strategy for a single key for this run, can be repeated
+    #[arg(long = "key-strategy", value_name = "KEY=STRATEGY", value_parser = parse_key_strategy)]
+    pub key_strategies: Vec<(String, MaskStrategy)>,
+    /// find sensitive values under any key: email, phone, credit_card, iban, ipv4, ipv6, ssn,
+    /// jwt, aws_access_key, github_token, gitlab_token, slack_token, private_key, high_entropy
+    #[arg(long = "detect", value_name = "DETECTORS", value_delimiter = ',')]
+    pub detect: Vec<Detector>,
+    /// minimal Shannon entropy (bits per character) for the high_entropy detector, 4.0 by default
+    #[arg(long = "entropy-threshold", value_name = "BITS")]
+    pub entropy_threshold: Option<f64>,
+    /// mask only detected parts of the text instead of the whole value
+    #[arg(long = "detect-substrings")]
+    pub detect_substrings: bool,
+    /// file with the secret for the hash strategy, HIDE_HASH_SECRET env variable is used otherwise
+    #[arg(long = "hash-key-file", value_name = "FILE")]
+    pub hash_key_file: Option<PathBuf>,
+    /// number of hex characters kept from the hash, 16 by default
+    #[arg(long = "hash-length", value_name = "LENGTH")]
+    pub hash_length: Option<usize>,
+    /// text added in front of every hash
+    #[arg(long = "hash-prefix", value_name = "PREFIX")]
+    pub hash_prefix: Option<String>,
+    /// file with the secret for the fpe strategy, HIDE_FPE_KEY env variable is used otherwise
+    #[arg(long = "fpe-key-file", value_name = "FILE")]
+    pub fpe_key_file: Option<PathBuf>,
+    /// seed for the faker strategy, so the same values always get the same replacements
+    #[arg(long = "seed", value_name = "SEED")]
+    pub seed: Option<u64>,
+    /// which values share numbering of the pseudonym strategy: run (default) or key
+    #[arg(long = "pseudonym-scope", value_name = "SCOPE")]
+    pub pseudonym_scope: Option<PseudonymScope>,
+    /// text in front of the pseudonym number, `user` or the key name by default; with the
+    /// key scope it goes before the key name
+    #[arg(long = "pseudonym-prefix", value_name = "PREFIX")]
+    pub pseudonym_prefix: Option<String>,
+    /// write pseudonyms with their original values to the JSON file, for later auditing
+    #[arg(long = "pseudonym-map", value_name = "FILE")]
+    pub pseudonym_map: Option<PathBuf>,
+    /// vault file for the token strategy, stored next to the config file by default
+    #[arg(long = "vault", value_name = "FILE")]
+    pub vault_file: Option<PathBuf>,
+    /// file with the vault secret, HIDE_VAULT_KEY env variable is used otherwise
+    #[arg(long = "vault-key-file", value_name = "FILE")]
+    pub vault_key_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HideCommand {
+    /// put original values back in place of tokens and decrypt values hidden with fpe
+    Restore(RestoreArgs),
+    /// manage stored keys of the user config, or of the profile given with --profile
+    #[command(subcommand)]
+    Keys(KeysCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// print stored keys, one per line
+    List,
+    /// add keys to hide
+    Add {
+        #[arg(required = true, value_delimiter = ',')]
+        keys: Vec<String>,
+    },
+    /// remove keys from hiding
+    Remove {
+        #[arg(required = true, value_delimiter = ',')]
+        keys: Vec<String>,
+    },
+    /// remove all stored keys
+    Clear,
+    /// print built-in presets with their keys
+    Presets,
+    /// add keys from a file: JSON array if it has .json extension, one key per line otherwise
+    Import { file: PathBuf },
+    /// print stored keys as text or JSON
+    Export {
+        /// text with one key per line, or json array
+        #[arg(long = "format", value_name = "FORMAT", default_value_t = KeysFormat::Text)]
+        format: KeysFormat,
+        /// path to the output file, stdout if not given or `-`
+        #[arg(short = 'o', long = "output", value_name = "FILE")]
+        output_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// path to the JSON, YAML, TOML or NDJSON file with tokens, stdin if not given or `-`
+    #[arg(short = 'i', long = "input", value_name = "FILE")]
+    pub input_file: Option<PathBuf>,
+    /// path to the output file, stdout if not given or `-`
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    pub output_file: Option<PathBuf>,
+    /// input format: json, yaml, toml or ndjson, guessed by the file extension by default
+    #[arg(long = "format", value_name = "FORMAT")]
+    pub format: Option<Format>,
+    /// output format: json, yaml, toml or ndjson, same as the input by default
+    #[arg(long = "output-format", value_name = "FORMAT")]
+    pub output_format: Option<Format>,
+    /// what to do with malformed NDJSON lines: skip, pass or abort
+    #[arg(long = "on-error", value_name = "POLICY", default_value_t = OnError::Abort)]
+    pub on_error: OnError,
+    /// change only masked values, keeping whitespace, comments, key order and numbers of JSON as is
+    #[arg(long = "preserve-format")]
+    pub preserve_format: bool,
+    /// vault file with original values, stored next to the config file by default
+    #[arg(long = "vault", value_name = "FILE")]
+    pub vault_file: Option<PathBuf>,
+    /// file with the vault secret, HIDE_VAULT_KEY env variable is used otherwise
+    #[arg(long = "vault-key-file", value_name = "FILE")]
+    pub vault_key_file: Option<PathBuf>,
+    /// strategy the values were hidden with, values hidden with fpe are decrypted
+    #[arg(short = 's', long = "strategy", value_name = "STRATEGY")]
+    pub strategy: Option<MaskStrategy>,
+    /// strategy a single key was hidden with, can be repeated
+    #[arg(long = "key-strategy", value_name = "KEY=STRATEGY", value_parser = parse_key_strategy)]
+    pub key_strategies: Vec<(String, MaskStrategy)>,
+    /// file with the secret for the fpe strategy, HIDE_FPE_KEY env variable is used otherwise
+    #[arg(long = "fpe-key-file", value_name = "FILE")]
+    pub fpe_key_file: Option<PathBuf>,
+}
+
+fn parse_key_strategy(s: &str) -> Result<(String, MaskStrategy), String> {
+    let (key, strategy) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=STRATEGY, got '{}'", s))?;
+    Ok((key.to_owned(), strategy.parse()?))
 }
@@ -0,0 +1,161 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::fs::{self, Metadata};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Edits a file in place: output goes to a temporary file next to it, which replaces
+/// the original only when everything is written, so the file is never left half done.
+pub struct InPlace {
+    path: PathBuf,
+    backup_suffix: String,
+    metadata: Metadata,
+    temp: NamedTempFile,
+}
+
+impl InPlace {
+    /// Remembers the state of the file before it's read. Empty suffix means no backup.
+    pub fn begin(path: &Path, backup_suffix: String) -> Result<Self> {
+        // edit the file a symlink points to, not the symlink itself
+        let path = fs::canonicalize(path)
+            .with_context(|| format!("could not read file: {}", path.display()))?;
+        let metadata = fs::metadata(&path)
+            .with_context(|| format!("could not read file: {}", path.display()))?;
+        // same directory, so the rename stays on one file system and is atomic
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let temp = tempfile::Builder::new()
+            .prefix(".hide-")
+            .tempfile_in(dir)
+            .with_context(|| format!("could not create temporary file in {}", dir.display()))?;
+        Ok(InPlace {
+            path,
+            backup_suffix,
+            metadata,
+            temp,
+        })
+    }
+
+    /// Where the output should be written.
+    pub fn temp_path(&self) -> &Path {
+        self.temp.path()
+    }
+
+    /// Replaces the original with the output, after making a backup if asked.
+    /// The temporary file is removed instead if the original changed in the meantime.
+    pub fn commit(self) -> Result<()> {
+        let current = fs::metadata(&self.path)
+            .with_context(|| format!("could not read file: {}", self.path.display()))?;
+        if changed(&self.metadata, &current) {
+            bail!(
+                "{} changed on disk while it was processed, not overwriting it",
+                self.path.display()
+            );
+        }
+        if !self.backup_suffix.is_empty() {
+            let mut backup = OsString::from(self.path.as_os_str());
+            backup.push(&self.backup_suffix);
+            fs::copy(&self.path, &backup).with_context(|| {
+                format!("could not write backup: {}", Path::new(&backup).display())
+            })?;
+        }
+        fs::set_permissions(self.temp.path(), self.metadata.permissions())
+            .with_context(|| format!("could not copy permissions of {}", self.path.display()))?;
+        self.temp
+            .persist(&self.path)
+            .with_context(|| format!("could not write file: {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Checks whether both paths lead to the same existing file.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Writes the file through a temporary file next to it, so a crash never leaves it half
+/// written. A new file is readable only by the owner.
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut temp = tempfile::Builder::new()
+        .prefix(".hide-")
+        .tempfile_in(dir)
+        .with_context(|| format!("could not create temporary file in {}", dir.display()))?;
+    temp.write_all(content)?;
+    temp.as_file().sync_all()?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(temp.path(), metadata.permissions())?;
+    }
+    temp.persist(path)?;
+    Ok(())
+}
+
+fn changed(before: &Metadata, after: &Metadata) -> bool {
+    before.len() != after.len() || before.modified().ok() != after.modified().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_commit_with_backup() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("data.json");
+        fs::write(&path, "original").unwrap();
+
+        let in_place = InPlace::begin(&path, String::from(".bak")).unwrap();
+        fs::write(in_place.temp_path(), "masked").unwrap();
+        in_place.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "masked");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("data.json.bak")).unwrap(),
+            "original"
+        );
+        // only the file and its backup are left
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("data.json");
+        fs::write(&path, "original").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let in_place = InPlace::begin(&path, String::new()).unwrap();
+        fs::write(in_place.temp_path(), "masked").unwrap();
+        in_place.commit().unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn test_refuse_changed_file() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("data.json");
+        fs::write(&path, "original").unwrap();
+
+        let in_place = InPlace::begin(&path, String::new()).unwrap();
+        fs::write(in_place.temp_path(), "masked").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+
+        assert!(in_place.commit().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -1,8 +1,9 @@
-#[allow(dead_code)]
+#[allow(dead_code, clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Error {
     KeyAlreadyExists(String),
     KeyNotFound(String),
+    InvalidPattern(String, String),
     StorageFull,
     StorageReadError,
     StorageWriteError,
@@ -17,6 +18,9 @@ impl std::fmt::Display for Error {
                 write!(f, "Key '{}' already exists in storage", key)
             }
             Error::KeyNotFound(key) => write!(f, "Key '{}' not found in storage", key),
+            Error::InvalidPattern(key, msg) => {
+                write!(f, "Key pattern '{}' is invalid: {}", key, msg)
+            }
             Error::StorageFull => write!(f, "Storage is full"),
             Error::StorageReadError => write!(f, "Error reading from storage"),
             Error::StorageWriteError => write!(f, "Error writing to storage"),
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Format of exported and imported keys, written as `text` or `json`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeysFormat {
+    /// one key per line
+    #[default]
+    Text,
+    /// array of strings
+    Json,
+}
+
+impl KeysFormat {
+    /// Writes keys sorted, so exports of the same keys are always the same.
+    pub fn write(&self, keys: &HashSet<String>) -> Result<String> {
+        let mut keys: Vec<&String> = keys.iter().collect();
+        keys.sort();
+        match self {
+            KeysFormat::Text => Ok(keys
+                .iter()
+                .map(|key| format!("{}\n", key))
+                .collect::<String>()),
+            KeysFormat::Json => Ok(serde_json::to_string_pretty(&keys)? + "\n"),
+        }
+    }
+
+    /// Parses keys, empty lines of text are skipped.
+    pub fn parse(&self, text: &str) -> Result<Vec<String>> {
+        match self {
+            KeysFormat::Text => Ok(text
+                .lines()
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_owned)
+                .collect()),
+            KeysFormat::Json => Ok(serde_json::from_str(text)?),
+        }
+    }
+
+    /// Reads keys from a file, JSON if it has `.json` extension and text otherwise.
+    pub fn read(path: &Path) -> Result<Vec<String>> {
+        let format = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => KeysFormat::Json,
+            _ => KeysFormat::Text,
+        };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("could not read file: {}", path.display()))?;
+        format
+            .parse(&text)
+            .with_context(|| format!("could not parse keys from {}", path.display()))
+    }
+}
+
+impl FromStr for KeysFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(KeysFormat::Text),
+            "json" => Ok(KeysFormat::Json),
+            _ => Err(format!(
+                "unknown keys format '{}', expected one of: text, json",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KeysFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeysFormat::Text => "text",
+            KeysFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export_and_import() {
+        let keys = HashSet::from(["password", "#/db/token", "*secret*"].map(String::from));
+        for format in [KeysFormat::Text, KeysFormat::Json] {
+            let text = format.write(&keys).unwrap();
+            let parsed: HashSet<String> = format.parse(&text).unwrap().into_iter().collect();
+            assert_eq!(parsed, keys, "{}", format);
+        }
+        assert_eq!(
+            KeysFormat::Text.write(&keys).unwrap(),
+            "#/db/token\n*secret*\npassword\n"
+        );
+        assert_eq!(
+            KeysFormat::Text.parse("\n  email \n\nphone\n").unwrap(),
+            ["email", "phone"]
+        );
+    }
+}
@@ -1,35 +1,156 @@
-#[allow(dead_code)]
 use std::collections::HashSet;
 
-use error::Error;
+use path::PathRule;
+use regex::RegexSet;
 
 type Result = std::result::Result<(), Error>;
 
 mod error;
+mod keys_file;
+mod path;
+mod pattern;
+mod preset;
+
+pub use error::Error;
+pub use keys_file::KeysFormat;
+pub use path::{JsonPath, PathSegment};
+pub use preset::Preset;
 
 pub trait KeysStorage {
     fn put(&mut self, key: &str) -> Result;
     fn remove(&mut self, key: &str) -> Result;
+    /// Removes all keys.
+    fn clear(&mut self);
     fn contains(&self, key: &str) -> bool;
+    /// Checks path rules against the location of the value.
+    fn contains_path(&self, path: &JsonPath) -> bool;
     fn all(&self) -> HashSet<String>;
 }
 
+/// Keeps literal keys, glob patterns (`*password*`), regexes (`/^api[_-]?key$/i`)
+/// and path rules (`$.customers[*].name`, `#/customers/0/name`).
+/// Patterns and paths are compiled once when keys are loaded or changed.
+#[derive(Clone)]
 pub struct InMemoryKeysStorage {
     storage: HashSet<String>,
+    patterns: RegexSet,
+    paths: Vec<PathRule>,
+    normalize: bool,
+    // literal keys after normalization, used only if `normalize` is enabled
+    normalized: HashSet<String>,
+    // keys of enabled presets, matched like stored keys but not returned by `all`
+    presets: HashSet<String>,
+}
+
+/// Lowercases the key and removes separators, so `phoneNumber`, `phone_number`,
+/// `PhoneNumber` and `phone-number` all become `phonenumber`.
+pub fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| !matches!(c, '_' | '-' | '.') && !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Checks whether the key is matched as it is, not as a pattern or a path.
+pub fn is_literal(key: &str) -> bool {
+    !pattern::is_pattern(key) && !path::is_path_rule(key)
 }
 
 impl InMemoryKeysStorage {
+    #[allow(dead_code)]
     fn new() -> Self {
         InMemoryKeysStorage {
             storage: HashSet::new(),
+            patterns: RegexSet::empty(),
+            paths: Vec::new(),
+            normalize: false,
+            normalized: HashSet::new(),
+            presets: HashSet::new(),
         }
     }
 
-    pub fn init_with(set: &HashSet<String>) -> Self {
-        InMemoryKeysStorage {
-            storage: set.to_owned(),
+    pub fn init_with(set: &HashSet<String>) -> std::result::Result<Self, Error> {
+        for key in set {
+            validate(key)?;
         }
+        let mut storage = InMemoryKeysStorage {
+            storage: set.to_owned(),
+            patterns: RegexSet::empty(),
+            paths: Vec::new(),
+            normalize: false,
+            normalized: HashSet::new(),
+            presets: HashSet::new(),
+        };
+        storage.compile_patterns();
+        storage.compile_paths();
+        Ok(storage)
     }
+
+    /// Matches keys ignoring case and separators (`_`, `-`, `.` and spaces).
+    pub fn normalize_keys(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self.compile_paths();
+        self.normalize_literals();
+        self
+    }
+
+    /// Matches keys of the presets too, without storing them.
+    pub fn with_presets(mut self, presets: impl IntoIterator<Item = Preset>) -> Self {
+        self.presets = presets
+            .into_iter()
+            .flat_map(|preset| preset.keys())
+            .map(|key| key.to_string())
+            .collect();
+        self.compile_patterns();
+        self.compile_paths();
+        self.normalize_literals();
+        self
+    }
+
+    // stored keys and keys of presets
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.storage.iter().chain(&self.presets)
+    }
+
+    fn compile_patterns(&mut self) {
+        let patterns = self.keys().filter_map(|key| pattern::to_regex(key));
+        // every pattern is validated before it gets into storage
+        self.patterns = RegexSet::new(patterns).expect("patterns are validated");
+    }
+
+    // names in the rules are normalized here, so each path is normalized only once
+    fn compile_paths(&mut self) {
+        let normalize = self.normalize;
+        self.paths = self
+            .keys()
+            .filter(|key| path::is_path_rule(key))
+            .map(|key| PathRule::parse(key).expect("paths are validated"))
+            .map(|rule| match normalize {
+                true => rule.normalized(normalize_key),
+                false => rule,
+            })
+            .collect();
+    }
+
+    fn normalize_literals(&mut self) {
+        self.normalized = match self.normalize {
+            true => self
+                .keys()
+                .filter(|key| is_literal(key))
+                .map(|key| normalize_key(key))
+                .collect(),
+            false => HashSet::new(),
+        };
+    }
+}
+
+fn validate(key: &str) -> Result {
+    if path::is_path_rule(key) {
+        return PathRule::parse(key)
+            .map(|_| ())
+            .map_err(|msg| Error::InvalidPattern(key.to_owned(), msg));
+    }
+    pattern::validate(key).map_err(|msg| Error::InvalidPattern(key.to_owned(), msg))
 }
 
 impl KeysStorage for InMemoryKeysStorage {
@@ -37,21 +158,67 @@ impl KeysStorage for InMemoryKeysStorage {
         if key.is_empty() {
             return Err(Error::Other(String::from("Can't save empty string")));
         }
+        validate(key)?;
         match self.storage.insert(key.to_string()) {
-            true => Ok(()),
+            true => {
+                if pattern::is_pattern(key) {
+                    self.compile_patterns();
+                } else if path::is_path_rule(key) {
+                    self.compile_paths();
+                } else {
+                    self.normalize_literals();
+                }
+                Ok(())
+            }
             false => Err(Error::KeyAlreadyExists(key.to_string())),
         }
     }
 
     fn remove(&mut self, key: &str) -> Result {
         match self.storage.remove(key) {
-            true => Ok(()),
+            true => {
+                if pattern::is_pattern(key) {
+                    self.compile_patterns();
+                } else if path::is_path_rule(key) {
+                    self.compile_paths();
+                } else {
+                    self.normalize_literals();
+                }
+                Ok(())
+            }
             false => Err(Error::KeyNotFound(key.to_string())),
         }
     }
 
+    fn clear(&mut self) {
+        self.storage.clear();
+        self.compile_patterns();
+        self.compile_paths();
+        self.normalize_literals();
+    }
+
     fn contains(&self, key: &str) -> bool {
-        self.storage.contains(key)
+        if self.storage.contains(key) || self.presets.contains(key) || self.patterns.is_match(key) {
+            return true;
+        }
+        if self.normalize {
+            let key = normalize_key(key);
+            return self.normalized.contains(&key) || self.patterns.is_match(&key);
+        }
+        false
+    }
+
+    fn contains_path(&self, path: &JsonPath) -> bool {
+        if self.paths.is_empty() {
+            return false;
+        }
+        match self.normalize {
+            true => {
+                let path = path.normalized(normalize_key);
+                self.paths.iter().any(|rule| rule.matches(&path))
+            }
+            false => self.paths.iter().any(|rule| rule.matches(path)),
+        }
     }
 
     fn all(&self) -> HashSet<String> {
@@ -96,6 +263,32 @@ mod test {
         assert!(!storage.contains("word1"));
     }
 
+    #[test]
+    fn test_clear() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("word").unwrap();
+        storage.put("*secret*").unwrap();
+        storage.put("$.user.name").unwrap();
+        storage.clear();
+        assert!(storage.all().is_empty());
+        assert!(!storage.contains("word"));
+        assert!(!storage.contains("top_secret"));
+    }
+
+    #[test]
+    fn test_presets_are_matched_but_not_stored() {
+        let storage = InMemoryKeysStorage::new()
+            .with_presets([Preset::Credentials, Preset::PiiBasic])
+            .normalize_keys(true);
+
+        assert!(storage.contains("email"));
+        assert!(storage.contains("firstName"));
+        assert!(storage.contains("db_password"));
+        assert!(!storage.contains("country"));
+        assert!(storage.all().is_empty());
+    }
+
     #[test]
     fn test_put_existing_key() {
         let mut storage = InMemoryKeysStorage::new();
@@ -150,6 +343,194 @@ mod test {
         assert!(storage.remove("").is_err()); // Assuming that removing an empty string returns an error
     }
 
+    #[test]
+    fn test_contains_glob() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("*password*").unwrap();
+        assert!(storage.contains("password"));
+        assert!(storage.contains("db_password"));
+        assert!(storage.contains("passwordHash"));
+        assert!(!storage.contains("username"));
+    }
+
+    #[test]
+    fn test_contains_regex() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("/^api[_-]?key$/i").unwrap();
+        assert!(storage.contains("api_key"));
+        assert!(storage.contains("API-KEY"));
+        assert!(storage.contains("apiKey"));
+        assert!(!storage.contains("api_key_id"));
+    }
+
+    #[test]
+    fn test_remove_pattern() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("*token").unwrap();
+        assert!(storage.contains("access_token"));
+
+        storage.remove("*token").unwrap();
+        assert!(!storage.contains("access_token"));
+    }
+
+    #[test]
+    fn test_put_invalid_regex() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        assert!(storage.put("/(unclosed/").is_err());
+        assert!(storage.all().is_empty());
+    }
+
+    #[test]
+    fn test_init_with_patterns() {
+        let keys = HashSet::from([String::from("name"), String::from("*secret*")]);
+        let storage = InMemoryKeysStorage::init_with(&keys).unwrap();
+
+        assert!(storage.contains("name"));
+        assert!(storage.contains("client_secret"));
+        assert!(!storage.contains("surname"));
+
+        let keys = HashSet::from([String::from("/[/")]);
+        assert!(InMemoryKeysStorage::init_with(&keys).is_err());
+    }
+
+    #[test]
+    fn test_normalize_key() {
+        assert_eq!(normalize_key("phoneNumber"), "phonenumber");
+        assert_eq!(normalize_key("phone_number"), "phonenumber");
+        assert_eq!(normalize_key("PhoneNumber"), "phonenumber");
+        assert_eq!(normalize_key("phone-number"), "phonenumber");
+        assert_eq!(normalize_key("Phone Number"), "phonenumber");
+    }
+
+    #[test]
+    fn test_contains_normalized() {
+        let mut storage = InMemoryKeysStorage::new().normalize_keys(true);
+
+        storage.put("phone_number").unwrap();
+        assert!(storage.contains("phone_number"));
+        assert!(storage.contains("phoneNumber"));
+        assert!(storage.contains("PhoneNumber"));
+        assert!(storage.contains("phone-number"));
+        assert!(!storage.contains("phone"));
+    }
+
+    #[test]
+    fn test_not_normalized_by_default() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("phone_number").unwrap();
+        assert!(!storage.contains("phoneNumber"));
+    }
+
+    #[test]
+    fn test_remove_normalized() {
+        let mut storage = InMemoryKeysStorage::new().normalize_keys(true);
+
+        storage.put("phone_number").unwrap();
+        storage.put("phoneNumber").unwrap();
+        storage.remove("phone_number").unwrap();
+        assert!(storage.contains("phone-number"));
+
+        storage.remove("phoneNumber").unwrap();
+        assert!(!storage.contains("phone-number"));
+    }
+
+    #[test]
+    fn test_pattern_matches_normalized_key() {
+        let mut storage = InMemoryKeysStorage::new().normalize_keys(true);
+
+        storage.put("*password*").unwrap();
+        assert!(storage.contains("DB_PASSWORD"));
+    }
+
+    #[test]
+    fn test_all_keeps_original_keys() {
+        let keys = HashSet::from([String::from("phone_number")]);
+        let storage = InMemoryKeysStorage::init_with(&keys)
+            .unwrap()
+            .normalize_keys(true);
+
+        assert_eq!(storage.all(), keys);
+    }
+
+    fn path(segments: &[PathSegment]) -> JsonPath {
+        let mut path = JsonPath::new();
+        segments.iter().for_each(|s| path.push(s.clone()));
+        path
+    }
+
+    #[test]
+    fn test_contains_path() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("$.customers[*].name").unwrap();
+        let customer_name = path(&[
+            PathSegment::Key(String::from("customers")),
+            PathSegment::Index(3),
+            PathSegment::Key(String::from("name")),
+        ]);
+        let product_name = path(&[
+            PathSegment::Key(String::from("products")),
+            PathSegment::Index(3),
+            PathSegment::Key(String::from("name")),
+        ]);
+        assert!(storage.contains_path(&customer_name));
+        assert!(!storage.contains_path(&product_name));
+        assert!(!storage.contains("name"));
+
+        storage.remove("$.customers[*].name").unwrap();
+        assert!(!storage.contains_path(&customer_name));
+    }
+
+    #[test]
+    fn test_contains_json_pointer() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("#/db/password").unwrap();
+        let db_password = path(&[
+            PathSegment::Key(String::from("db")),
+            PathSegment::Key(String::from("password")),
+        ]);
+        assert!(storage.contains_path(&db_password));
+    }
+
+    #[test]
+    fn test_contains_normalized_path() {
+        let mut storage = InMemoryKeysStorage::new().normalize_keys(true);
+
+        storage.put("$.user.phone_number").unwrap();
+        let phone = path(&[
+            PathSegment::Key(String::from("User")),
+            PathSegment::Key(String::from("phoneNumber")),
+        ]);
+        assert!(storage.contains_path(&phone));
+
+        let keys = HashSet::from([String::from("$.user.phone_number")]);
+        let storage = InMemoryKeysStorage::init_with(&keys).unwrap();
+        assert!(!storage.contains_path(&phone));
+        assert!(storage.normalize_keys(true).contains_path(&phone));
+    }
+
+    #[test]
+    fn test_contains_dollar_key() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        storage.put("$ref").unwrap();
+        assert!(storage.contains("$ref"));
+        assert!(!storage.contains_path(&path(&[PathSegment::Key(String::from("id"))])));
+    }
+
+    #[test]
+    fn test_put_invalid_path() {
+        let mut storage = InMemoryKeysStorage::new();
+
+        assert!(storage.put("$.a[?(@.b)]").is_err());
+    }
+
     #[test]
     fn test_all_works() {
         let mut storage = InMemoryKeysStorage::new();
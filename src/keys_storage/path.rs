@@ -0,0 +1,374 @@
+use std::fmt;
+
+/// Location of a value in the JSON document, tracked while walking through it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl JsonPath {
+    pub fn new() -> Self {
+        JsonPath::default()
+    }
+
+    pub fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) {
+        self.segments.pop();
+    }
+
+    /// Returns the closest key in the path, used to pick a strategy for array items.
+    pub fn last_key(&self) -> Option<&str> {
+        self.segments
+            .iter()
+            .rev()
+            .find_map(|segment| match segment {
+                PathSegment::Key(key) => Some(key.as_str()),
+                PathSegment::Index(_) => None,
+            })
+    }
+
+    /// Returns the path with every key passed through `normalize`.
+    pub fn normalized(&self, normalize: impl Fn(&str) -> String) -> JsonPath {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => PathSegment::Key(normalize(key)),
+                PathSegment::Index(index) => PathSegment::Index(*index),
+            })
+            .collect();
+        JsonPath { segments }
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(key) if is_identifier(key) => write!(f, ".{}", key)?,
+                PathSegment::Key(key) => write!(f, "['{}']", key.replace('\'', "\\'"))?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Rule that hides values at some location, written as JSONPath (`$.customers[*].name`)
+/// or as RFC 6901 JSON Pointer in URI fragment form (`#/customers/0/name`).
+#[derive(Debug, Clone)]
+pub struct PathRule {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// direct child of the current value
+    Child(Selector),
+    /// any value below the current one, `..` in JSONPath
+    Descendant(Selector),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Index(usize),
+    // JSON Pointer tokens can point to a key or to an array index
+    Token(String),
+    Wildcard,
+}
+
+/// Keys like `$ref` or `$schema` are plain keys, only `$`, `$.…` and `$[…` are JSONPath.
+pub fn is_path_rule(key: &str) -> bool {
+    key == "$"
+        || key.starts_with("$.")
+        || key.starts_with("$[")
+        || key.starts_with("#/")
+        || key == "#"
+}
+
+impl PathRule {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        if let Some(pointer) = rule.strip_prefix('#') {
+            return parse_pointer(pointer);
+        }
+        match rule.strip_prefix('$') {
+            Some(path) => parse_json_path(path),
+            None => Err(String::from("path should start with '$' or '#/'")),
+        }
+    }
+
+    /// Returns the rule with every name passed through `normalize`, to match paths
+    /// normalized the same way.
+    pub fn normalized(self, normalize: impl Fn(&str) -> String) -> Self {
+        let selector = |selector| match selector {
+            Selector::Name(name) => Selector::Name(normalize(&name)),
+            Selector::Token(token) => Selector::Token(normalize(&token)),
+            selector => selector,
+        };
+        let steps = self
+            .steps
+            .into_iter()
+            .map(|step| match step {
+                Step::Child(child) => Step::Child(selector(child)),
+                Step::Descendant(descendant) => Step::Descendant(selector(descendant)),
+            })
+            .collect();
+        PathRule { steps }
+    }
+
+    pub fn matches(&self, path: &JsonPath) -> bool {
+        matches_steps(&self.steps, &path.segments)
+    }
+}
+
+fn matches_steps(steps: &[Step], segments: &[PathSegment]) -> bool {
+    let Some((step, steps)) = steps.split_first() else {
+        return segments.is_empty();
+    };
+    match step {
+        Step::Child(selector) => match segments.split_first() {
+            Some((segment, segments)) => {
+                selector_matches(selector, segment) && matches_steps(steps, segments)
+            }
+            None => false,
+        },
+        Step::Descendant(selector) => (0..segments.len()).any(|i| {
+            selector_matches(selector, &segments[i]) && matches_steps(steps, &segments[i + 1..])
+        }),
+    }
+}
+
+fn selector_matches(selector: &Selector, segment: &PathSegment) -> bool {
+    match (selector, segment) {
+        (Selector::Wildcard, _) => true,
+        (Selector::Name(name), PathSegment::Key(key)) => name == key,
+        (Selector::Index(index), PathSegment::Index(i)) => index == i,
+        (Selector::Token(token), PathSegment::Key(key)) => token == key,
+        (Selector::Token(token), PathSegment::Index(i)) => token == &i.to_string(),
+        _ => false,
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Result<PathRule, String> {
+    let pointer = percent_decode(pointer)?;
+    if pointer.is_empty() {
+        return Ok(PathRule { steps: Vec::new() });
+    }
+    let Some(pointer) = pointer.strip_prefix('/') else {
+        return Err(String::from("JSON Pointer should start with '/'"));
+    };
+    let steps = pointer
+        .split('/')
+        .map(|token| Step::Child(Selector::Token(token.replace("~1", "/").replace("~0", "~"))))
+        .collect();
+    Ok(PathRule { steps })
+}
+
+fn percent_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid percent encoding at {}", i))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|e| e.to_string())
+}
+
+fn parse_json_path(path: &str) -> Result<PathRule, String> {
+    let chars: Vec<char> = path.chars().collect();
+    let mut steps = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let descendant = chars[i..].starts_with(&['.', '.']);
+        let selector = if descendant || chars[i] == '.' {
+            i += if descendant { 2 } else { 1 };
+            if i < chars.len() && chars[i] == '[' {
+                let (selector, end) = parse_brackets(&chars, i)?;
+                i = end;
+                selector
+            } else {
+                let start = i;
+                while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                match name.as_str() {
+                    "" => return Err(format!("missing name at {}", start)),
+                    "*" => Selector::Wildcard,
+                    _ => Selector::Name(name),
+                }
+            }
+        } else if chars[i] == '[' {
+            let (selector, end) = parse_brackets(&chars, i)?;
+            i = end;
+            selector
+        } else {
+            return Err(format!("unexpected '{}' at {}", chars[i], i));
+        };
+        steps.push(match descendant {
+            true => Step::Descendant(selector),
+            false => Step::Child(selector),
+        });
+    }
+    Ok(PathRule { steps })
+}
+
+// parses `[*]`, `[0]`, `['name']` or `["name"]` starting at `[`, returns selector and position after `]`
+fn parse_brackets(chars: &[char], start: usize) -> Result<(Selector, usize), String> {
+    let mut i = start + 1;
+    let selector = match chars.get(i) {
+        Some('\'') | Some('"') => {
+            let quote = chars[i];
+            let mut name = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some('\\') => {
+                        name.extend(chars.get(i + 1));
+                        i += 2;
+                    }
+                    Some(c) if *c == quote => break,
+                    Some(c) => {
+                        name.push(*c);
+                        i += 1;
+                    }
+                    None => return Err(format!("unclosed quote at {}", start)),
+                }
+            }
+            i += 1;
+            Selector::Name(name)
+        }
+        Some('*') => {
+            i += 1;
+            Selector::Wildcard
+        }
+        _ => {
+            let begin = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let index: String = chars[begin..i].iter().collect();
+            let index = index
+                .parse()
+                .map_err(|_| format!("unsupported selector at {}", start))?;
+            Selector::Index(index)
+        }
+    };
+    match chars.get(i) {
+        Some(']') => Ok((selector, i + 1)),
+        _ => Err(format!("expected ']' at {}", i)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path(segments: &[&str]) -> JsonPath {
+        let mut path = JsonPath::new();
+        for segment in segments {
+            match segment.parse() {
+                Ok(index) => path.push(PathSegment::Index(index)),
+                Err(_) => path.push(PathSegment::Key(segment.to_string())),
+            }
+        }
+        path
+    }
+
+    fn matches(rule: &str, segments: &[&str]) -> bool {
+        PathRule::parse(rule).unwrap().matches(&path(segments))
+    }
+
+    #[test]
+    fn test_json_path_child_and_wildcard() {
+        assert!(matches("$.customers[*].name", &["customers", "0", "name"]));
+        assert!(!matches("$.customers[*].name", &["products", "0", "name"]));
+        assert!(!matches("$.customers[*].name", &["customers", "0"]));
+        assert!(matches("$.customers.*", &["customers", "email"]));
+        assert!(matches("$['first name']", &["first name"]));
+        assert!(matches("$.items[1]", &["items", "1"]));
+        assert!(!matches("$.items[1]", &["items", "2"]));
+    }
+
+    #[test]
+    fn test_json_path_descendant() {
+        assert!(matches("$..password", &["password"]));
+        assert!(matches("$..password", &["a", "0", "b", "password"]));
+        assert!(matches(
+            "$..customers..name",
+            &["x", "customers", "0", "name"]
+        ));
+        assert!(!matches("$..password", &["password", "hash"]));
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        assert!(matches("#/customers/0/name", &["customers", "0", "name"]));
+        assert!(!matches("#/customers/0/name", &["customers", "1", "name"]));
+        assert!(matches("#/a~1b/c~0d", &["a/b", "c~d"]));
+        assert!(matches("#/first%20name", &["first name"]));
+    }
+
+    #[test]
+    fn test_dollar_keys_are_not_paths() {
+        assert!(!is_path_rule("$ref"));
+        assert!(!is_path_rule("$schema"));
+        assert!(is_path_rule("$"));
+        assert!(is_path_rule("$.id"));
+        assert!(is_path_rule("$['id']"));
+    }
+
+    #[test]
+    fn test_json_pointer_normalized() {
+        let normalize = |key: &str| key.to_lowercase().replace('_', "");
+        let rule = PathRule::parse("#/user/phone_number")
+            .unwrap()
+            .normalized(normalize);
+        assert!(rule.matches(&path(&["user", "phoneNumber"]).normalized(normalize)));
+        assert!(!matches("#/user/phone_number", &["user", "phoneNumber"]));
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        assert!(PathRule::parse("$.").is_err());
+        assert!(PathRule::parse("$[?(@.a)]").is_err());
+        assert!(PathRule::parse("$['unclosed").is_err());
+        assert!(PathRule::parse("#customers").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            path(&["customers", "0", "name"]).to_string(),
+            "$.customers[0].name"
+        );
+        assert_eq!(path(&["first name"]).to_string(), "$['first name']");
+    }
+}
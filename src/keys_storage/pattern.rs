@@ -0,0 +1,128 @@
+use super::path;
+use regex::Regex;
+
+/// Returns regex for keys written as a glob (`*password*`) or as a regex between slashes
+/// with optional flags (`/^api[_-]?key$/i`), `None` for literal keys.
+pub fn to_regex(key: &str) -> Option<String> {
+    if path::is_path_rule(key) {
+        return None;
+    }
+    if let Some(regex) = parse_regex(key) {
+        return Some(regex);
+    }
+    if is_glob(key) {
+        return Some(glob_to_regex(key));
+    }
+    None
+}
+
+pub fn is_pattern(key: &str) -> bool {
+    to_regex(key).is_some()
+}
+
+/// Checks that the pattern compiles, returns error message otherwise.
+pub fn validate(key: &str) -> Result<(), String> {
+    match to_regex(key) {
+        Some(regex) => Regex::new(&regex).map(|_| ()).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+fn parse_regex(key: &str) -> Option<String> {
+    let body = key.strip_prefix('/')?;
+    let end = body.rfind('/')?;
+    let (regex, flags) = (&body[..end], &body[end + 1..]);
+    if regex.is_empty() || !flags.chars().all(|c| "imsxU".contains(c)) {
+        return None;
+    }
+    match flags.is_empty() {
+        true => Some(regex.to_owned()),
+        false => Some(format!("(?{}){}", flags, regex)),
+    }
+}
+
+fn is_glob(key: &str) -> bool {
+    key.contains(['*', '?', '['])
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let mut class = String::new();
+                let mut closed = false;
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    class.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        class.push('\\');
+                    }
+                    class.push(c);
+                }
+                match closed {
+                    true => regex.push_str(&format!("[{}]", class)),
+                    // not a class, match the bracket literally
+                    false => regex.push_str(&regex::escape(&format!("[{}", class))),
+                }
+            }
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(pattern: &str, key: &str) -> bool {
+        Regex::new(&to_regex(pattern).unwrap())
+            .unwrap()
+            .is_match(key)
+    }
+
+    #[test]
+    fn test_literal_is_not_pattern() {
+        assert!(!is_pattern("password"));
+        assert!(!is_pattern("$.users[*].name"));
+        assert!(!is_pattern("a/b"));
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(matches("*password*", "db_password"));
+        assert!(matches("*password*", "passwordHash"));
+        assert!(!matches("*password*", "Password"));
+        assert!(matches("key?", "key1"));
+        assert!(!matches("key?", "key12"));
+        assert!(matches("card.[0-9]", "card.7"));
+        assert!(!matches("card.[!0-9]", "card.7"));
+        assert!(!matches("a.b*", "axb"));
+    }
+
+    #[test]
+    fn test_regex() {
+        assert!(matches("/^api[_-]?key$/i", "API_KEY"));
+        assert!(matches("/^api[_-]?key$/i", "apikey"));
+        assert!(!matches("/^api[_-]?key$/", "API_KEY"));
+        assert!(!matches("/^api[_-]?key$/i", "api_key_id"));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(validate("/(unclosed/").is_err());
+        assert!(validate("/valid/").is_ok());
+        assert!(validate("literal").is_ok());
+    }
+}
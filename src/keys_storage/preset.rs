@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Built-in set of keys, used together with stored keys but never stored itself.
+///
+/// Written in config and command line as `pii-basic`, `credentials`, `payment` or `health`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub enum Preset {
+    /// names, contacts, addresses and birth dates
+    PiiBasic,
+    /// passwords, secrets, tokens and API keys
+    Credentials,
+    /// card and bank account details
+    Payment,
+    /// medical and health insurance records
+    Health,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::PiiBasic,
+        Preset::Credentials,
+        Preset::Payment,
+        Preset::Health,
+    ];
+
+    /// Keys and patterns of the preset, combine it with `--normalize-keys` to catch
+    /// `firstName` or `First-Name` as well.
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            Preset::PiiBasic => &[
+                "name",
+                "first_name",
+                "last_name",
+                "middle_name",
+                "full_name",
+                "email",
+                "email_address",
+                "phone",
+                "phone_number",
+                "mobile",
+                "address",
+                "street",
+                "city",
+                "zip",
+                "zip_code",
+                "postal_code",
+                "dob",
+                "date_of_birth",
+                "birth_date",
+                "birthday",
+            ],
+            Preset::Credentials => &[
+                "*password*",
+                "passwd",
+                "*secret*",
+                "*token*",
+                "api_key",
+                "apikey",
+                "access_key",
+                "private_key",
+                "authorization",
+                "credentials",
+            ],
+            Preset::Payment => &[
+                "card_number",
+                "credit_card",
+                "cc_number",
+                "pan",
+                "cvv",
+                "cvc",
+                "card_expiry",
+                "iban",
+                "bic",
+                "swift",
+                "account_number",
+                "routing_number",
+            ],
+            Preset::Health => &[
+                "diagnosis",
+                "diagnoses",
+                "condition",
+                "medication",
+                "medications",
+                "prescription",
+                "allergies",
+                "blood_type",
+                "medical_record_number",
+                "mrn",
+                "patient_id",
+                "insurance_number",
+                "icd_code",
+            ],
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pii-basic" => Ok(Preset::PiiBasic),
+            "credentials" => Ok(Preset::Credentials),
+            "payment" => Ok(Preset::Payment),
+            "health" => Ok(Preset::Health),
+            _ => Err(format!(
+                "unknown preset '{}', expected one of: pii-basic, credentials, payment, health",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Preset::PiiBasic => "pii-basic",
+            Preset::Credentials => "credentials",
+            Preset::Payment => "payment",
+            Preset::Health => "health",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<String> for Preset {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Preset> for String {
+    fn from(value: Preset) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_preset_keys_are_valid() {
+        for preset in Preset::ALL {
+            for key in preset.keys() {
+                assert!(super::super::validate(key).is_ok(), "{}: {}", preset, key);
+            }
+            assert_eq!(preset.to_string().parse::<Preset>(), Ok(preset));
+        }
+    }
+}
@@ -1,153 +1,647 @@
+use crate::batch::{Batch, Target};
 use crate::config::Config;
-use crate::hide_args::HideArgs;
-use crate::keys_storage::{InMemoryKeysStorage, KeysStorage};
-use anyhow::{Context, Result};
-use clap::Parser;
+use crate::detect::{Detectors, DEFAULT_ENTROPY_THRESHOLD};
+use crate::format::ndjson::{self, OnError};
+use crate::format::{Format, Input};
+use crate::hide_args::{HideArgs, HideCommand, KeysCommand, RestoreArgs};
+use crate::in_place::InPlace;
+use crate::keys_storage::{
+    InMemoryKeysStorage, JsonPath, KeysFormat, KeysStorage, PathSegment, Preset,
+};
+use crate::mask::faker::Faker;
+use crate::mask::fpe::{self, Encryptor};
+use crate::mask::hash::{self, Hasher};
+use crate::mask::pseudonym::Pseudonymizer;
+use crate::mask::vault::Vault;
+use crate::mask::{MaskStrategy, Masker, Restorer, Transform};
+use anyhow::{bail, Context, Result};
+use clap::{CommandFactory, Parser};
 use serde_json::{json, Map, Value};
 use simple_logger::SimpleLogger;
-use std::collections::HashSet;
-use std::fs;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
+mod batch;
 mod config;
+mod detect;
+mod format;
 mod hide_args;
+mod in_place;
 mod keys_storage;
+mod mask;
+mod stream;
 
 fn main() -> Result<()> {
-    let args = HideArgs::parse();
+    match run(HideArgs::parse()) {
+        // the reader of the pipe is gone, e.g. `hide -i big.json | head`, nothing to report
+        Err(error) if is_broken_pipe(&error) => Ok(()),
+        result => result,
+    }
+}
 
+fn run(args: HideArgs) -> Result<()> {
     // init logger if debug enabled
     if args.debug {
         SimpleLogger::new().init().unwrap();
         log::info!("debug enabled, logger initialized.");
     }
 
-    let config: Config =
-        confy::load("hide", "hide-cfg").with_context(|| "could not parse config")?;
-    let sensitive_keys = config.sensitive_keys.unwrap_or(HashSet::new());
-    let mut storage = InMemoryKeysStorage::init_with(&sensitive_keys);
+    let mut config: Config = match args.no_user_config {
+        true => Config::default(),
+        false => confy::load("hide", "hide-cfg").with_context(|| "could not parse config")?,
+    };
+    let profile = args.profile.as_deref();
 
-    // add keys if any
-    if !args.add_keys.is_empty() {
-        add_keys(&mut storage, &args.add_keys);
+    if let Some(HideCommand::Keys(command)) = &args.command {
+        if args.no_user_config {
+            bail!("keys are stored in the user config, it can't be used with --no-user-config");
+        }
+        return keys(&mut config, profile, command);
+    }
+    if !args.remove_keys.is_empty() || !args.add_keys.is_empty() {
+        if args.no_user_config {
+            bail!("--add-keys and --remove-keys change the user config, it can't be used with --no-user-config");
+        }
+        let mut own_keys = own_keys(&config, profile)?;
+        add_keys(&mut own_keys, &args.add_keys)?;
+        remove_keys(&mut own_keys, &args.remove_keys)?;
+        store_keys(&mut config, profile, &own_keys)?;
     }
 
-    // remove keys if any
-    if !args.remove_keys.is_empty() {
-        remove_keys(&mut storage, &args.remove_keys);
+    // the policy file of the project goes on top of the user config
+    let input_file = match &args.command {
+        Some(HideCommand::Restore(restore_args)) => restore_args.input_file.clone(),
+        _ => args.input_file.clone(),
+    };
+    let policy_file = match &args.config_file {
+        Some(path) => Some(path.to_owned()),
+        None => config::discover(stdio_path(input_file).as_deref()),
+    };
+    if let Some(path) = policy_file {
+        log::debug!("using policy file: {}", path.display());
+        config = config.merge(config::read(&path)?);
+    }
+    if let Some(profile) = profile {
+        config = config.with_profile(profile)?;
     }
+    let sensitive_keys = config.sensitive_keys.clone().unwrap_or_default();
+    let normalize_keys = args.normalize_keys || config.normalize_keys.unwrap_or(false);
+    let mut presets = config.presets.clone().unwrap_or_default();
+    presets.extend(&args.presets);
+    let storage = InMemoryKeysStorage::init_with(&sensitive_keys)
+        .with_context(|| "could not load sensitive keys from config")?
+        .with_presets(presets)
+        .normalize_keys(normalize_keys);
 
-    if !args.remove_keys.is_empty() || !args.add_keys.is_empty() {
-        log::info!("storing config...");
-        let config = Config {
-            sensitive_keys: Some(storage.all()),
-        };
-        confy::store("hide", "hide-cfg", &config)
-            .with_context(|| "could not store config")
-            .unwrap();
+    if let Some(HideCommand::Restore(restore_args)) = args.command {
+        return restore(&storage, &config, restore_args, normalize_keys);
     }
 
-    // nothing to do if input not specified
-    if let None = args.input_file {
+    // without input file the input is read from stdin, unless nothing is piped in
+    let keys_changed = !args.add_keys.is_empty() || !args.remove_keys.is_empty();
+    if args.input_file.is_none() && io::stdin().is_terminal() {
+        if keys_changed {
+            return Ok(());
+        }
+        HideArgs::command().print_help()?;
+        bail!("nothing to hide, give an input file with -i or pipe it to stdin");
+    }
+    // keys are managed without input, unless it's given explicitly
+    if args.input_file.is_none() && keys_changed {
+        eprintln!("stdin is not read when keys are changed, use -i - to hide it");
         return Ok(());
     }
 
-    let input_path = args.input_file.unwrap();
+    let mut masker = build_masker(&args, &config, normalize_keys)?;
 
-    let input_path = input_path.to_str().unwrap();
+    if let Some(input) = args
+        .input_file
+        .as_deref()
+        .filter(|path| batch::is_batch(path))
+    {
+        let target = match (&args.output_dir, &args.in_place) {
+            (Some(output_dir), _) => Target::Dir(output_dir.to_owned()),
+            (None, Some(backup_suffix)) => Target::InPlace(backup_suffix.to_owned()),
+            (None, None) => bail!(
+                "{} is not a single file, use --output-dir or --in-place",
+                input.display()
+            ),
+        };
+        let batch = Batch {
+            target,
+            include: args.include.clone(),
+            exclude: args.exclude.clone(),
+            format: args.format,
+            output_format: args.output_format,
+            preserve_format: args.preserve_format || config.preserve_format.unwrap_or(false),
+            on_error: args.on_error,
+        };
+        return batch.run(input, &storage, masker);
+    }
+    if args.output_dir.is_some() {
+        bail!("--output-dir requires a directory or a glob pattern as input");
+    }
 
-    let file_str = fs::read_to_string(input_path)
-        .with_context(|| format!("could not read file: {}", input_path))?;
+    let input_file = stdio_path(args.input_file);
+    let format = Format::resolve(args.format, input_file.as_deref());
+    let output_format = args.output_format.unwrap_or(format);
+    let json = format == Format::Json && output_format == Format::Json;
+    if args.preserve_format && !json {
+        bail!("--preserve-format works only with JSON input and output");
+    }
+    // the config is shared by files of every format, so it's only a warning there
+    let preserve_format = args.preserve_format || config.preserve_format.unwrap_or(false);
+    if preserve_format && !json {
+        eprintln!(
+            "preserve_format from the config works only with JSON input and output, ignoring it"
+        );
+    }
+    let output_file = stdio_path(args.output_file);
+    let in_place = begin_in_place(input_file.as_deref(), output_file.as_deref(), args.in_place)?;
+    let job = Job {
+        output_file: match &in_place {
+            Some(in_place) => Some(in_place.temp_path().to_owned()),
+            None => output_file,
+        },
+        input_file,
+        format,
+        output_format,
+        preserve_format,
+        on_error: args.on_error,
+    };
+    let result = process(&storage, &mut masker, job);
+    // keep tokens and pseudonyms of everything written, even if it failed half way
+    masker.save()?;
+    result?;
+    match in_place {
+        Some(in_place) => in_place.commit(),
+        None => Ok(()),
+    }
+}
 
-    let input = serde_json::from_str::<Value>(&file_str)
-        .with_context(|| format!("could not parse file: {}", input_path))?;
+fn build_masker(args: &HideArgs, config: &Config, normalize_keys: bool) -> Result<Masker> {
+    let mut key_strategies = config.key_strategies.clone().unwrap_or_default();
+    key_strategies.extend(args.key_strategies.iter().cloned());
+    let mut masker = Masker::new(
+        args.strategy
+            .clone()
+            .or(config.strategy.clone())
+            .unwrap_or_default(),
+        key_strategies,
+    )?
+    .normalize_keys(normalize_keys)?;
+    let mut detectors = config.detectors.clone().unwrap_or_default();
+    detectors.extend(&args.detect);
+    if !detectors.is_empty() {
+        let substrings = args.detect_substrings || config.detect_substrings.unwrap_or(false);
+        let entropy_threshold = args
+            .entropy_threshold
+            .or(config.entropy_threshold)
+            .unwrap_or(DEFAULT_ENTROPY_THRESHOLD);
+        masker = masker.with_detectors(
+            Detectors::new(detectors, substrings).with_entropy_threshold(entropy_threshold),
+        );
+    }
+    if masker.uses(&MaskStrategy::Hash) {
+        let key_file = args.hash_key_file.clone().or(config.hash_key_file.clone());
+        let Some(secret) = Hasher::load_secret(key_file.as_deref())? else {
+            bail!(
+                "hash strategy requires a secret, set {} or use --hash-key-file",
+                hash::SECRET_ENV
+            );
+        };
+        let hasher = Hasher::new(
+            secret,
+            args.hash_length.or(config.hash_length),
+            args.hash_prefix.clone().or(config.hash_prefix.clone()),
+        )?;
+        masker = masker.with_hasher(hasher);
+    }
+    if masker.uses(&MaskStrategy::Fpe) {
+        let key_file = args.fpe_key_file.clone().or(config.fpe_key_file.clone());
+        masker = masker.with_encryptor(encryptor(key_file)?);
+    }
+    if masker.uses(&MaskStrategy::Faker) {
+        masker = masker.with_faker(Faker::new(args.seed.or(config.seed)));
+    }
+    if masker.uses(&MaskStrategy::Pseudonym) {
+        let pseudonymizer = Pseudonymizer::new(
+            args.pseudonym_scope
+                .or(config.pseudonym_scope)
+                .unwrap_or_default(),
+            args.pseudonym_prefix
+                .clone()
+                .or(config.pseudonym_prefix.clone()),
+        )
+        .with_map_file(args.pseudonym_map.clone().or(config.pseudonym_map.clone()));
+        masker = masker.with_pseudonymizer(pseudonymizer);
+    }
+    if masker.uses(&MaskStrategy::Token) {
+        let vault = open_vault(
+            args.vault_file.clone().or(config.vault_file.clone()),
+            args.vault_key_file
+                .clone()
+                .or(config.vault_key_file.clone()),
+        )?;
+        masker = masker.with_vault(vault);
+    }
+    Ok(masker)
+}
 
-    log::debug!("input:\n{:?}", input);
+fn restore(
+    storage: &impl KeysStorage,
+    config: &Config,
+    args: RestoreArgs,
+    normalize_keys: bool,
+) -> Result<()> {
+    // strategies of the keys tell which values were encrypted
+    let mut key_strategies = config.key_strategies.clone().unwrap_or_default();
+    key_strategies.extend(args.key_strategies);
+    let mut masker = Masker::new(
+        args.strategy
+            .or(config.strategy.clone())
+            .unwrap_or_default(),
+        key_strategies,
+    )?
+    .normalize_keys(normalize_keys)?;
+    let encrypted = masker.uses(&MaskStrategy::Fpe);
+    if encrypted {
+        masker = masker.with_encryptor(encryptor(
+            args.fpe_key_file.or(config.fpe_key_file.clone()),
+        )?);
+    }
+    // encrypted values don't need the vault, it's opened only if there are tokens to restore
+    let vault_file = vault_path(args.vault_file.or(config.vault_file.clone()))?;
+    let vault = match encrypted && !vault_file.exists() {
+        true => None,
+        false => Some(open_vault(
+            Some(vault_file),
+            args.vault_key_file.or(config.vault_key_file.clone()),
+        )?),
+    };
+    let mut restorer = Restorer::new(vault, masker);
+    let input_file = stdio_path(args.input_file);
+    let format = Format::resolve(args.format, input_file.as_deref());
+    let output_format = args.output_format.unwrap_or(format);
+    if args.preserve_format && (format != Format::Json || output_format != Format::Json) {
+        bail!("--preserve-format works only with JSON input and output");
+    }
+    let output_file = stdio_path(args.output_file);
+    let in_place = begin_in_place(input_file.as_deref(), output_file.as_deref(), None)?;
+    let job = Job {
+        output_file: match &in_place {
+            Some(in_place) => Some(in_place.temp_path().to_owned()),
+            None => output_file,
+        },
+        input_file,
+        format,
+        output_format,
+        preserve_format: args.preserve_format,
+        on_error: args.on_error,
+    };
+    process(storage, &mut restorer, job)?;
+    match in_place {
+        Some(in_place) => in_place.commit(),
+        None => Ok(()),
+    }
+}
 
-    let output = hide_by_keys(&storage, &input);
-    let output = serde_json::to_string_pretty(&output).unwrap();
-    match args.output_file {
-        // print to console if output file not specified
-        None => println!("{output}"),
-        // write to file
-        Some(path) => fs::write(path, output).unwrap(),
+// output written over the input would truncate it before it's read, so it's edited in
+// place then, without a backup unless `--in-place` asks for one
+fn begin_in_place(
+    input_file: Option<&Path>,
+    output_file: Option<&Path>,
+    backup_suffix: Option<String>,
+) -> Result<Option<InPlace>> {
+    let backup_suffix = backup_suffix.or_else(|| match (input_file, output_file) {
+        (Some(input), Some(output)) if in_place::same_file(input, output) => Some(String::new()),
+        _ => None,
+    });
+    let Some(backup_suffix) = backup_suffix else {
+        return Ok(None);
+    };
+    let Some(path) = input_file else {
+        bail!("--in-place requires an input file");
     };
+    Ok(Some(InPlace::begin(path, backup_suffix)?))
+}
+
+/// What to read, where to write it and how.
+struct Job {
+    input_file: Option<PathBuf>,
+    output_file: Option<PathBuf>,
+    format: Format,
+    output_format: Format,
+    preserve_format: bool,
+    on_error: OnError,
+}
+
+fn process(storage: &impl KeysStorage, transform: &mut impl Transform, job: Job) -> Result<()> {
+    run_job(storage, transform, job)?;
+    transform.check()
+}
+
+// picks the way to go through the input: JSON and NDJSON are streamed, other formats
+// are read as a whole
+fn run_job(storage: &impl KeysStorage, transform: &mut impl Transform, job: Job) -> Result<()> {
+    let same_format = |format| job.format == format && job.output_format == format;
+    if job.preserve_format && same_format(Format::Json) {
+        return stream_json(job.input_file, job.output_file, false, |input, output| {
+            stream::preserve(input, output, storage, transform)
+        });
+    }
+    if same_format(Format::Ndjson) {
+        return stream_ndjson(job.input_file, job.output_file, job.on_error, |record| {
+            hide_by_keys(storage, transform, record)
+        });
+    }
+    if same_format(Format::Json) {
+        return stream_json(job.input_file, job.output_file, true, |input, output| {
+            stream::mask(input, output, storage, transform)
+        });
+    }
+    let input = format::read_input(job.input_file.as_deref(), job.format, job.on_error)?;
+
+    log::debug!("input:\n{:?}", input.documents);
+
+    let output: Vec<Value> = input
+        .documents
+        .iter()
+        .map(|document| hide_by_keys(storage, transform, document))
+        .collect();
+    write_output(job.output_file, job.output_format, &input, &output)
+}
+
+fn encryptor(key_file: Option<PathBuf>) -> Result<Encryptor> {
+    let Some(secret) = Encryptor::load_secret(key_file.as_deref())? else {
+        bail!(
+            "fpe strategy requires a secret, set {} or use --fpe-key-file",
+            fpe::SECRET_ENV
+        );
+    };
+    Encryptor::new(&secret)
+}
+
+fn vault_path(vault_file: Option<PathBuf>) -> Result<PathBuf> {
+    match vault_file {
+        Some(path) => Ok(path),
+        // keep vault next to the config file by default
+        None => Ok(confy::get_configuration_file_path("hide", "hide-cfg")
+            .with_context(|| "could not find config directory")?
+            .with_file_name("vault")),
+    }
+}
+
+fn open_vault(vault_file: Option<PathBuf>, key_file: Option<PathBuf>) -> Result<Vault> {
+    let vault_file = vault_path(vault_file)?;
+    log::debug!("using vault: {}", vault_file.display());
+    let secret = Vault::load_secret(key_file.as_deref())?;
+    Vault::open(&vault_file, &secret)
+}
+
+// masks NDJSON record by record, so the whole file is never kept in memory
+fn stream_ndjson(
+    input_file: Option<PathBuf>,
+    output_file: Option<PathBuf>,
+    on_error: OnError,
+    transform: impl FnMut(&Value) -> Value,
+) -> Result<()> {
+    let input = open_input(input_file.as_deref())?;
+    let output = open_output(output_file.as_deref())?;
+    ndjson::stream(input, output, on_error, transform)
+}
+
+// masks JSON while reading it, `newline` ends stdout output like in the tree-based path
+fn stream_json(
+    input_file: Option<PathBuf>,
+    output_file: Option<PathBuf>,
+    newline: bool,
+    mask: impl FnOnce(Box<dyn BufRead>, &mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let input = open_input(input_file.as_deref())?;
+    let mut output = open_output(output_file.as_deref())?;
+    mask(input, &mut output).with_context(|| {
+        format!(
+            "could not parse {}",
+            format::describe(input_file.as_deref())
+        )
+    })?;
+    if newline && output_file.is_none() {
+        output.write_all(b"\n")?;
+    }
+    output.flush()?;
     Ok(())
 }
 
-fn add_keys(storage: &mut impl KeysStorage, keys: &Vec<String>) {
+/// `-` stands for stdin or stdout, same as no path at all.
+fn stdio_path(path: Option<PathBuf>) -> Option<PathBuf> {
+    path.filter(|path| path != Path::new("-"))
+}
+
+fn open_input(input_file: Option<&Path>) -> Result<Box<dyn BufRead>> {
+    let Some(input_file) = input_file else {
+        return Ok(Box::new(io::stdin().lock()));
+    };
+    let input = File::open(input_file)
+        .with_context(|| format!("could not read file: {}", input_file.display()))?;
+    Ok(Box::new(BufReader::new(input)))
+}
+
+fn open_output(output_file: Option<&Path>) -> Result<BufWriter<Box<dyn Write>>> {
+    let output: Box<dyn Write> = match output_file {
+        None => Box::new(io::stdout().lock()),
+        Some(path) => Box::new(
+            File::create(path)
+                .with_context(|| format!("could not write file: {}", path.display()))?,
+        ),
+    };
+    Ok(BufWriter::new(output))
+}
+
+fn write_output(
+    output_file: Option<PathBuf>,
+    format: Format,
+    input: &Input,
+    output: &[Value],
+) -> Result<()> {
+    let source = (format == input.format).then_some(input.text.as_str());
+    let mut text = format
+        .write(output, source)
+        .with_context(|| format!("could not write {} output", format))?;
+    // printed output ends with a new line, files are written as is
+    if output_file.is_none() {
+        text.push('\n');
+    }
+    let mut writer = open_output(output_file.as_deref())?;
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|error| error.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+fn keys(config: &mut Config, profile: Option<&str>, command: &KeysCommand) -> Result<()> {
+    let mut storage = own_keys(config, profile)?;
+    match command {
+        KeysCommand::List => {
+            print!("{}", KeysFormat::Text.write(&storage.all())?);
+            return Ok(());
+        }
+        KeysCommand::Presets => {
+            for preset in Preset::ALL {
+                println!("{}: {}", preset, preset.keys().join(", "));
+            }
+            return Ok(());
+        }
+        KeysCommand::Export {
+            format,
+            output_file,
+        } => {
+            let text = format.write(&storage.all())?;
+            let mut writer = open_output(stdio_path(output_file.clone()).as_deref())?;
+            writer.write_all(text.as_bytes())?;
+            writer.flush()?;
+            return Ok(());
+        }
+        KeysCommand::Add { keys } => add_keys(&mut storage, keys)?,
+        KeysCommand::Remove { keys } => remove_keys(&mut storage, keys)?,
+        KeysCommand::Clear => storage.clear(),
+        KeysCommand::Import { file } => add_keys(&mut storage, &KeysFormat::read(file)?)?,
+    }
+    store_keys(config, profile, &storage)
+}
+
+// keys stored in the selected profile only, inherited keys stay where they are
+fn own_keys(config: &Config, profile: Option<&str>) -> Result<InMemoryKeysStorage> {
+    InMemoryKeysStorage::init_with(&config.own_keys(profile))
+        .with_context(|| "could not load sensitive keys from config")
+}
+
+fn store_keys(
+    config: &mut Config,
+    profile: Option<&str>,
+    storage: &impl KeysStorage,
+) -> Result<()> {
+    log::info!("storing config...");
+    config.set_own_keys(profile, storage.all());
+    confy::store("hide", "hide-cfg", config).with_context(|| "could not store config")
+}
+
+// keys that are already there are reported and skipped, invalid ones fail before anything is stored
+fn add_keys(storage: &mut impl KeysStorage, keys: &[String]) -> Result<()> {
     log::debug!("adding keys...");
-    keys.iter()
-        .map(|key| (key, storage.put(key)))
-        .map(|(key, result)| match result {
-            Ok(()) => format!("saved {}", key),
-            Err(error) => format!("{}", error),
-        })
-        .for_each(|msg| log::debug!("{}", msg));
+    for key in keys {
+        match storage.put(key) {
+            Ok(()) => log::debug!("saved {}", key),
+            Err(error @ keys_storage::Error::KeyAlreadyExists(_)) => eprintln!("{}", error),
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(())
 }
 
-fn remove_keys(storage: &mut impl KeysStorage, keys: &Vec<String>) {
+fn remove_keys(storage: &mut impl KeysStorage, keys: &[String]) -> Result<()> {
     log::debug!("removing keys...");
-    keys.iter()
-        .map(|keys| (keys, storage.remove(keys)))
-        .map(|(keys, result)| match result {
-            Ok(()) => format!("removed {}", keys),
-            Err(error) => format!("{}", error),
-        })
-        .for_each(|msg| log::debug!("{}", msg));
+    for key in keys {
+        match storage.remove(key) {
+            Ok(()) => log::debug!("removed {}", key),
+            Err(error @ keys_storage::Error::KeyNotFound(_)) => eprintln!("{}", error),
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(())
 }
 
-fn hide_by_keys(storage: &impl KeysStorage, json: &Value) -> Value {
+fn hide_by_keys(storage: &impl KeysStorage, masker: &mut impl Transform, json: &Value) -> Value {
+    let mut path = JsonPath::new();
+    if storage.contains_path(&path) {
+        return masker.hide(json, &path).unwrap_or(Value::Null);
+    }
     match json {
-        Value::Array(_) => hide_by_keys_in_array(storage, json.as_array().unwrap()),
-        Value::Object(_) => hide_by_keys_in_map(storage, json.as_object().unwrap()),
-        _ => json.clone(),
+        Value::Array(_) => {
+            hide_by_keys_in_array(storage, masker, json.as_array().unwrap(), &mut path)
+        }
+        Value::Object(_) => {
+            hide_by_keys_in_map(storage, masker, json.as_object().unwrap(), &mut path)
+        }
+        _ => masker.inspect(json, &path).unwrap_or(Value::Null),
     }
 }
 
-fn hide_by_keys_in_map(storage: &impl KeysStorage, json: &Map<String, Value>) -> Value {
+fn hide_by_keys_in_map(
+    storage: &impl KeysStorage,
+    masker: &mut impl Transform,
+    json: &Map<String, Value>,
+    path: &mut JsonPath,
+) -> Value {
     let mut result_map = serde_json::Map::with_capacity(json.len());
     for (key, value) in json {
         log::debug!("key: {}, value: {}", key, value);
-        let value = if storage.contains(key) {
-            hide(value)
+        path.push(PathSegment::Key(key.to_owned()));
+        let value = if storage.contains(key) || storage.contains_path(path) {
+            masker.hide(value, path)
         } else if value.is_object() {
-            hide_by_keys_in_map(storage, value.as_object().unwrap())
+            Some(hide_by_keys_in_map(
+                storage,
+                masker,
+                value.as_object().unwrap(),
+                path,
+            ))
         } else if value.is_array() {
-            hide_by_keys_in_array(storage, &value.as_array().unwrap())
+            Some(hide_by_keys_in_array(
+                storage,
+                masker,
+                value.as_array().unwrap(),
+                path,
+            ))
         } else {
-            value.clone()
+            masker.inspect(value, path)
         };
-        result_map.insert(key.to_owned(), value);
+        path.pop();
+        if let Some(value) = value {
+            result_map.insert(key.to_owned(), value);
+        }
     }
     json!(result_map)
 }
 
-fn hide_by_keys_in_array(storage: &impl KeysStorage, json: &Vec<Value>) -> Value {
+fn hide_by_keys_in_array(
+    storage: &impl KeysStorage,
+    masker: &mut impl Transform,
+    json: &[Value],
+    path: &mut JsonPath,
+) -> Value {
     let mut result: Vec<Value> = Vec::with_capacity(json.len());
-    for item in json {
-        let item = match item {
-            Value::Array(_) => hide_by_keys_in_array(storage, item.as_array().unwrap()),
-            Value::Object(_) => hide_by_keys_in_map(storage, item.as_object().unwrap()),
-            _ => item.clone(),
+    for (index, item) in json.iter().enumerate() {
+        path.push(PathSegment::Index(index));
+        let item = if storage.contains_path(path) {
+            masker.hide(item, path)
+        } else {
+            match item {
+                Value::Array(_) => Some(hide_by_keys_in_array(
+                    storage,
+                    masker,
+                    item.as_array().unwrap(),
+                    path,
+                )),
+                Value::Object(_) => Some(hide_by_keys_in_map(
+                    storage,
+                    masker,
+                    item.as_object().unwrap(),
+                    path,
+                )),
+                _ => masker.inspect(item, path),
+            }
         };
-        result.push(item);
+        path.pop();
+        result.extend(item);
     }
     json!(result)
 }
-
-fn hide(value: &Value) -> Value {
-    let new_value = match value {
-        Value::Null => Value::Null,
-        Value::Bool(_) => Value::String(String::from("Bool")),
-        Value::Number(_) => Value::String(String::from("Number")),
-        Value::String(_) => Value::String(String::from("String")),
-        Value::Array(values) => Value::Array(values.iter().map(|v| hide(v)).collect()),
-        Value::Object(values) => Value::Object(
-            values
-                .iter()
-                .map(|(k, v)| (k.to_owned(), hide(v)))
-                .collect(),
-        ),
-    };
-    new_value
-}
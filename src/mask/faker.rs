@@ -0,0 +1,513 @@
+use super::{fake_chars, fake_number};
+use crate::keys_storage::normalize_key;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::LazyLock;
+
+static EMAIL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").unwrap());
+static UUID_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$")
+        .unwrap()
+});
+static DATE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{4}-\d{1,2}-\d{1,2}|\d{1,2}[./-]\d{1,2}[./-]\d{4})([T ].*)?$").unwrap()
+});
+
+const FIRST_NAMES: &[&str] = &[
+    "Al", "Bo", "Eva", "Ian", "Kim", "Leo", "Mia", "Zoe", "Adam", "Anna", "Emma", "John", "Lily",
+    "Liam", "Mark", "Nora", "Ruth", "Alice", "David", "Ethan", "Grace", "Henry", "Julia", "Oscar",
+    "Sarah", "Amelia", "Daniel", "Hannah", "Oliver", "Sophia", "Thomas", "Abigail", "Charles",
+    "Eleanor", "Matthew", "Natalie", "Benjamin", "Caroline", "Jonathan", "Samantha",
+];
+const LAST_NAMES: &[&str] = &[
+    "Fox",
+    "Lee",
+    "Ng",
+    "Cole",
+    "Gray",
+    "King",
+    "Wood",
+    "Young",
+    "Adams",
+    "Baker",
+    "Clark",
+    "Evans",
+    "Green",
+    "Lewis",
+    "Scott",
+    "Walker",
+    "Wright",
+    "Turner",
+    "Parker",
+    "Miller",
+    "Carter",
+    "Bennett",
+    "Collins",
+    "Edwards",
+    "Johnson",
+    "Roberts",
+    "Mitchell",
+    "Anderson",
+    "Campbell",
+    "Harrison",
+    "Richardson",
+    "Williamson",
+];
+const CITIES: &[&str] = &[
+    "Bath",
+    "Lyon",
+    "Oslo",
+    "Bern",
+    "Dover",
+    "Salem",
+    "Tulsa",
+    "Exeter",
+    "Denver",
+    "Dublin",
+    "Austin",
+    "Boston",
+    "Oxford",
+    "Seattle",
+    "Bristol",
+    "Phoenix",
+    "Portland",
+    "Hamilton",
+    "Richmond",
+    "Columbus",
+    "Brighton",
+    "Arlington",
+    "Cambridge",
+    "Nashville",
+    "Greenville",
+    "Springfield",
+    "Westminster",
+];
+const STREETS: &[&str] = &[
+    "Oak",
+    "Elm",
+    "Main",
+    "Park",
+    "Pine",
+    "Hill",
+    "Lake",
+    "Maple",
+    "Cedar",
+    "River",
+    "Church",
+    "Station",
+    "Highland",
+    "Sunset",
+    "Washington",
+    "Meadow",
+];
+const STREET_SUFFIXES: &[&str] = &["St", "Ave", "Rd", "Ln", "Dr", "Way", "Street", "Road"];
+const COMPANY_SUFFIXES: &[&str] = &["Inc", "LLC", "Ltd", "Group", "Labs", "Systems", "Partners"];
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// Kind of data a value holds, guessed by the value itself and by its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Email,
+    FirstName,
+    LastName,
+    FullName,
+    Phone,
+    Address,
+    City,
+    Uuid,
+    Date,
+    Company,
+    Other,
+}
+
+/// Replaces values with plausible fake data of the same kind: names, emails, phone numbers,
+/// addresses, UUIDs, dates and company names, keeping length, casing and digit groupings
+/// of the original where possible.
+///
+/// Every value is generated from the seed and the original value, so with the same seed
+/// the same value always gets the same replacement, across runs and files.
+#[derive(Clone)]
+pub struct Faker {
+    seed: u64,
+}
+
+impl Faker {
+    /// Creates faker with the given seed, or a random one.
+    pub fn new(seed: Option<u64>) -> Self {
+        Faker {
+            seed: seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        }
+    }
+
+    pub fn fake(&self, key: &str, value: &Value) -> Value {
+        match value {
+            Value::String(text) => {
+                let kind = kind(key, text);
+                let mut rng = self.rng(kind, &value.to_string());
+                Value::String(fake_text(&mut rng, kind, text))
+            }
+            Value::Number(number) => {
+                let mut rng = self.rng(Kind::Other, &number.to_string());
+                fake_number(&mut rng, value)
+            }
+            Value::Bool(flag) => {
+                let mut rng = self.rng(Kind::Other, &flag.to_string());
+                Value::Bool(rng.gen())
+            }
+            _ => value.clone(),
+        }
+    }
+
+    fn rng(&self, kind: Kind, value: &str) -> StdRng {
+        let digest = Sha256::new()
+            .chain_update(self.seed.to_le_bytes())
+            .chain_update(format!("{:?}", kind))
+            .chain_update(value)
+            .finalize();
+        StdRng::from_seed(digest.into())
+    }
+}
+
+fn kind(key: &str, text: &str) -> Kind {
+    if EMAIL_REGEX.is_match(text) {
+        return Kind::Email;
+    }
+    if UUID_REGEX.is_match(text) {
+        return Kind::Uuid;
+    }
+    if DATE_REGEX.is_match(text) {
+        return Kind::Date;
+    }
+    let key = normalize_key(key);
+    let has = |parts: &[&str]| parts.iter().any(|part| key.contains(part));
+    // `mailing_list` or `email_verified` aren't emails
+    let ends = |parts: &[&str]| parts.iter().any(|part| key.ends_with(part));
+    if key == "mail" || ends(&["email", "emailaddress"]) {
+        Kind::Email
+    } else if has(&["company", "organization", "organisation", "employer"]) {
+        Kind::Company
+    } else if has(&["firstname", "givenname", "forename"]) {
+        Kind::FirstName
+    } else if has(&["lastname", "surname", "familyname"]) {
+        Kind::LastName
+    } else if has(&["username", "login", "filename", "hostname"]) {
+        Kind::Other
+    } else if has(&["name"]) {
+        Kind::FullName
+    } else if has(&["phone", "mobile", "fax"]) {
+        Kind::Phone
+    } else if has(&["city", "town"]) {
+        Kind::City
+    } else if has(&["address", "street"]) && !key.starts_with("ip") && !key.starts_with("mac") {
+        Kind::Address
+    } else if has(&["uuid", "guid"]) {
+        Kind::Uuid
+    } else if has(&["date", "dob", "birth"]) && text.contains(|c: char| c.is_ascii_digit()) {
+        Kind::Date
+    } else {
+        Kind::Other
+    }
+}
+
+fn fake_text(rng: &mut StdRng, kind: Kind, text: &str) -> String {
+    let case = Case::of(text);
+    match kind {
+        Kind::Email => email(rng, text),
+        Kind::FirstName => case.apply(&pick(rng, FIRST_NAMES, text.chars().count())),
+        Kind::LastName => case.apply(&pick(rng, LAST_NAMES, text.chars().count())),
+        Kind::FullName => full_name(rng, text),
+        Kind::Phone => phone(rng, text),
+        Kind::Address => case.apply(&address(rng, text)),
+        Kind::City => case.apply(&pick(rng, CITIES, text.chars().count())),
+        Kind::Uuid => uuid(rng, text),
+        Kind::Date => date(rng, text),
+        Kind::Company => {
+            let name = pick(rng, LAST_NAMES, 0);
+            let company = match text.contains(char::is_whitespace) {
+                true => format!("{} {}", name, COMPANY_SUFFIXES.choose(rng).unwrap()),
+                false => name,
+            };
+            case.apply(&company)
+        }
+        Kind::Other => fake_chars(rng, text),
+    }
+}
+
+// random word of the length closest to `len`, any word if `len` is 0
+fn pick(rng: &mut StdRng, words: &[&str], len: usize) -> String {
+    let distance = |word: &&str| word.chars().count().abs_diff(len);
+    let best = match len {
+        0 => 0,
+        _ => words.iter().map(distance).min().unwrap_or(0),
+    };
+    let candidates: Vec<&&str> = words
+        .iter()
+        .filter(|word| len == 0 || distance(word) == best)
+        .collect();
+    candidates.choose(rng).unwrap().to_string()
+}
+
+// every word of the name replaced with a name of the same length
+fn full_name(rng: &mut StdRng, text: &str) -> String {
+    let words: Vec<&str> = text.split(' ').collect();
+    let last = words.len() - 1;
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            let names = match index == last && last > 0 {
+                true => LAST_NAMES,
+                false => FIRST_NAMES,
+            };
+            match word.is_empty() {
+                true => String::new(),
+                false => Case::of(word).apply(&pick(rng, names, word.chars().count())),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// `first.last@example.com`, with the separator and casing of the original local part
+fn email(rng: &mut StdRng, text: &str) -> String {
+    let local = text.split('@').next().unwrap_or_default();
+    let separator = local.chars().find(|c| matches!(c, '.' | '_' | '-'));
+    let first = pick(rng, FIRST_NAMES, 0);
+    let last = pick(rng, LAST_NAMES, 0);
+    let local = match separator {
+        Some(separator) => format!("{}{}{}", first, separator, last),
+        None => format!("{}{}", first, last),
+    };
+    let local = match Case::of(text) {
+        Case::Upper => local.to_uppercase(),
+        _ => local.to_lowercase(),
+    };
+    let domain = EMAIL_DOMAINS.choose(rng).unwrap();
+    match Case::of(text) {
+        Case::Upper => format!("{}@{}", local, domain.to_uppercase()),
+        _ => format!("{}@{}", local, domain),
+    }
+}
+
+// new digits in the same groups, the country code after `+` is kept
+fn phone(rng: &mut StdRng, text: &str) -> String {
+    let country_code = match text.strip_prefix('+') {
+        Some(rest) => {
+            1 + rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or_else(|| country_code_len(rest))
+        }
+        None => 0,
+    };
+    let (code, number) = text.split_at(country_code);
+    format!("{}{}", code, fake_chars(rng, number))
+}
+
+// length of the country code at the start of the digits, by the ITU numbering plan
+fn country_code_len(digits: &str) -> usize {
+    const TWO_DIGIT_CODES: &[&str] = &[
+        "20", "27", "30", "31", "32", "33", "34", "36", "39", "40", "41", "43", "44", "45", "46",
+        "47", "48", "49", "51", "52", "53", "54", "55", "56", "57", "58", "60", "61", "62", "63",
+        "64", "65", "66", "81", "82", "84", "86", "90", "91", "92", "93", "94", "95", "98",
+    ];
+    let len = if digits.starts_with(['1', '7']) {
+        1
+    } else if TWO_DIGIT_CODES.iter().any(|code| digits.starts_with(code)) {
+        2
+    } else {
+        3
+    };
+    len.min(digits.len())
+}
+
+// `12 Oak St`, house number has as many digits as the original one
+fn address(rng: &mut StdRng, text: &str) -> String {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let street = format!(
+        "{} {}",
+        STREETS.choose(rng).unwrap(),
+        STREET_SUFFIXES.choose(rng).unwrap()
+    );
+    let mut address = match digits {
+        0 => street,
+        // longer numbers don't fit u64, they are only random digits
+        1..=18 => {
+            let number = rng.gen_range(10u64.pow(digits as u32 - 1)..10u64.pow(digits as u32));
+            format!("{} {}", number, street)
+        }
+        _ => format!("{} {}", fake_chars(rng, &text[..digits]), street),
+    };
+    if text.contains(", ") {
+        address = format!("{}, {}", address, CITIES.choose(rng).unwrap());
+    }
+    address
+}
+
+// random hex digits with the same casing, the version digit is kept and the variant is RFC 4122
+fn uuid(rng: &mut StdRng, text: &str) -> String {
+    let upper = Case::of(text) == Case::Upper;
+    text.chars()
+        .enumerate()
+        .map(|(index, c)| match c {
+            '-' => c,
+            _ if index == 14 => c,
+            _ => {
+                let digit = match index {
+                    19 => rng.gen_range(8..12),
+                    _ => rng.gen_range(0..16),
+                };
+                let digit = std::char::from_digit(digit, 16).unwrap();
+                match upper {
+                    true => digit.to_ascii_uppercase(),
+                    false => digit,
+                }
+            }
+        })
+        .collect()
+}
+
+// year between 1950 and 2009, day and month up to 12, so they are valid in any order,
+// every field keeps its width
+fn date(rng: &mut StdRng, text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut groups = 0;
+    while groups < 3 {
+        let Some(start) = rest.find(|c: char| c.is_ascii_digit()) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let len = rest[start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len() - start);
+        let part = match len {
+            4 => rng.gen_range(1950..2010).to_string(),
+            1 => rng.gen_range(1..=9).to_string(),
+            _ => format!("{:0width$}", rng.gen_range(1..=12), width = len),
+        };
+        result.push_str(&part);
+        rest = &rest[start + len..];
+        groups += 1;
+    }
+    // time is kept as is
+    result.push_str(rest);
+    result
+}
+
+/// Casing of the original text, applied to the generated one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Upper,
+    Lower,
+    Title,
+}
+
+impl Case {
+    fn of(text: &str) -> Case {
+        let has_upper = text.chars().any(char::is_uppercase);
+        let has_lower = text.chars().any(char::is_lowercase);
+        match (has_upper, has_lower) {
+            (true, false) => Case::Upper,
+            (false, true) => Case::Lower,
+            _ => Case::Title,
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        match self {
+            Case::Upper => text.to_uppercase(),
+            Case::Lower => text.to_lowercase(),
+            Case::Title => text.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn fake(key: &str, value: &str) -> String {
+        let faked = Faker::new(Some(7)).fake(key, &json!(value));
+        faked.as_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_same_seed_same_values() {
+        let value = json!("jon.snow@winterfell.org");
+        let first = Faker::new(Some(1)).fake("email", &value);
+        assert_eq!(Faker::new(Some(1)).fake("contact", &value), first);
+        assert_ne!(Faker::new(Some(2)).fake("email", &value), first);
+    }
+
+    #[test]
+    fn test_kinds_keep_format() {
+        let email = fake("contact", "Jon.Snow@winterfell.org");
+        let (local, domain) = email.split_once('@').unwrap();
+        assert!(local.contains('.'), "{}", email);
+        assert!(domain.starts_with("example."), "{}", email);
+
+        let name = fake("full_name", "JON SNOW");
+        assert_eq!(name.len(), 8, "{}", name);
+        assert_eq!(name, name.to_uppercase());
+
+        let phone = fake("phone", "+380 (44) 123-45-67");
+        assert!(phone.starts_with("+380 ("), "{}", phone);
+        assert_eq!(phone.len(), "+380 (44) 123-45-67".len());
+        assert_eq!(&phone[8..10], ") ");
+        for number in ["+380501234567", "+14155550100", "+4915112345678"] {
+            let phone = fake("phone", number);
+            assert_eq!(phone.len(), number.len());
+            assert!(phone[1..].chars().all(|c| c.is_ascii_digit()), "{}", phone);
+        }
+        assert!(fake("phone", "+380501234567").starts_with("+380"));
+        assert!(fake("phone", "+14155550100").starts_with("+1"));
+        assert!(fake("phone", "+4915112345678").starts_with("+49"));
+
+        let uuid = fake("id", "123E4567-E89B-42D3-A456-426614174000");
+        assert!(UUID_REGEX.is_match(&uuid), "{}", uuid);
+        assert_eq!(&uuid[14..15], "4");
+        assert_eq!(uuid, uuid.to_uppercase());
+
+        let date = fake("created", "2021-03-04T10:00:00Z");
+        assert!(DATE_REGEX.is_match(&date), "{}", date);
+        assert!(date.ends_with("T10:00:00Z"));
+        for seed in 0..50 {
+            let date = Faker::new(Some(seed)).fake("dob", &json!("1.2.1990"));
+            assert_eq!(date.as_str().unwrap().len(), "1.2.1990".len(), "{}", date);
+        }
+
+        let address = fake("address", "221 Baker Street, London");
+        assert!(
+            address.chars().take(3).all(|c| c.is_ascii_digit()),
+            "{}",
+            address
+        );
+        assert!(address.contains(", "));
+        let address = fake("address", "123456789012345678901234 Long Road");
+        assert!(
+            address.chars().take(24).all(|c| c.is_ascii_digit()),
+            "{}",
+            address
+        );
+
+        assert!(fake("company", "Acme Corp").contains(' '));
+        assert_eq!(fake("city", "kyiv"), fake("city", "kyiv").to_lowercase());
+        assert_eq!(fake("note", "Ab-12").len(), 5);
+    }
+
+    #[test]
+    fn test_email_keys() {
+        assert_eq!(kind("email", "jon"), Kind::Email);
+        assert_eq!(kind("mail", "jon"), Kind::Email);
+        assert_eq!(kind("work_e-mail", "jon"), Kind::Email);
+        assert_eq!(kind("emailAddress", "jon"), Kind::Email);
+        assert_eq!(kind("mailing_list", "jon"), Kind::Other);
+        assert_eq!(kind("email_template", "jon"), Kind::Other);
+    }
+}
@@ -0,0 +1,415 @@
+use super::kdf;
+use ::fpe::ff1::{FlexibleNumeralString, FF1};
+use aes::Aes256;
+use anyhow::{bail, Context, Result};
+use serde_json::{Number, Value};
+use std::fs;
+use std::path::Path;
+
+/// environment variable with the secret used for format-preserving encryption
+pub const SECRET_ENV: &str = "HIDE_FPE_KEY";
+
+// first character and size of every alphabet that is encrypted on its own
+const ALPHABETS: [(char, u32); 3] = [('0', 10), ('a', 26), ('A', 26)];
+
+// FF1 needs at least a million possible values
+const MIN_DOMAIN: u64 = 1_000_000;
+
+// longer mixes of digits and letters rarely have an alphabet too short to encrypt on its own,
+// so cycle walking would take too long to find one
+const MAX_TOGETHER: usize = 64;
+
+// encrypted values have no header, so the salt is fixed and the secret is enough to decrypt
+const SALT: &[u8] = b"hide-fpe";
+
+/// Format-preserving encryption (FF1 with AES-256): digits stay digits and letters stay
+/// letters of the same case, other characters are kept, so encrypted values have the same
+/// length and format. Nothing but the secret is needed to decrypt them.
+///
+/// A kind of characters with too few of them to encrypt on its own, like `JS` in
+/// `Jon Snow`, is encrypted together with all other digits and letters, so they stay
+/// alphanumeric but can change their kind.
+pub struct Encryptor {
+    alphabets: Vec<Alphabet>,
+    // digits and letters of both cases as one alphabet
+    alphanumeric: FF1<Aes256>,
+}
+
+struct Alphabet {
+    first: char,
+    radix: u32,
+    cipher: FF1<Aes256>,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+impl Encryptor {
+    pub fn new(secret: &[u8]) -> Result<Self> {
+        if secret.is_empty() {
+            bail!("fpe secret can't be empty");
+        }
+        let key = kdf::derive_key(secret, SALT)?;
+        let alphabets = ALPHABETS
+            .iter()
+            .map(|&(first, radix)| Alphabet {
+                first,
+                radix,
+                cipher: FF1::new(&key, radix).expect("radix of the alphabet is valid"),
+            })
+            .collect();
+        let radix = ALPHABETS.iter().map(|(_, radix)| radix).sum();
+        let alphanumeric = FF1::new(&key, radix).expect("radix of all alphabets is valid");
+        Ok(Encryptor {
+            alphabets,
+            alphanumeric,
+        })
+    }
+
+    /// Reads the secret from the key file if given, otherwise from `HIDE_FPE_KEY`.
+    /// Returns `None` if neither is set.
+    pub fn load_secret(key_file: Option<&Path>) -> Result<Option<Vec<u8>>> {
+        if let Some(path) = key_file {
+            let secret = fs::read(path)
+                .with_context(|| format!("could not read fpe key file: {}", path.display()))?;
+            return Ok(Some(secret.trim_ascii_end().to_vec()));
+        }
+        Ok(std::env::var(SECRET_ENV)
+            .ok()
+            .filter(|secret| !secret.is_empty())
+            .map(String::into_bytes))
+    }
+
+    /// Encrypts strings and numbers, numbers stay numbers written with as many digits.
+    /// Strings with fewer than 4 digits and letters, with more than 64 of them but too
+    /// few of one kind, or with letters beyond ASCII, are masked with `*` and can't be
+    /// decrypted. Numbers with fewer than
+    /// 6 digits can't be encrypted at all.
+    pub fn encrypt(&self, value: &Value) -> Result<Value> {
+        if let Some(encrypted) = self.transform(value, Direction::Encrypt) {
+            return Ok(encrypted);
+        }
+        let text = match value {
+            Value::Number(_) => bail!("fpe can't encrypt numbers with fewer than 6 digits"),
+            Value::String(text) => text.to_owned(),
+            _ => value.to_string(),
+        };
+        let masked = text
+            .chars()
+            .map(|c| if c.is_alphanumeric() { '*' } else { c })
+            .collect();
+        Ok(Value::String(masked))
+    }
+
+    /// Decrypts strings and numbers anywhere in the value, everything else is kept.
+    pub fn decrypt(&self, value: &Value) -> Value {
+        match value {
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.decrypt(v)).collect()),
+            Value::Object(values) => Value::Object(
+                values
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), self.decrypt(v)))
+                    .collect(),
+            ),
+            _ => self
+                .transform(value, Direction::Decrypt)
+                .unwrap_or_else(|| value.clone()),
+        }
+    }
+
+    fn transform(&self, value: &Value, direction: Direction) -> Option<Value> {
+        match value {
+            Value::String(text) => self.text(text, direction).map(Value::String),
+            Value::Number(number) => {
+                // cycle walking: the result is encrypted again until it's a number written
+                // the same way, decryption walks the same cycle back
+                let digits = &self.alphabets[0];
+                let mut chars: Vec<char> = number.to_string().chars().collect();
+                loop {
+                    chars = digits.crypt(chars, direction)?;
+                    let text: String = chars.iter().collect();
+                    if let Some(number) = number_of(&text) {
+                        return Some(Value::Number(number));
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // every alphabet is encrypted on its own keeping the positions of its characters;
+    // if some of them is too short, all digits and letters are encrypted together
+    fn text(&self, text: &str, direction: Direction) -> Option<String> {
+        let mut chars: Vec<char> = text.chars().collect();
+        // letters of other scripts have no alphabet and would be left as plain text
+        if chars
+            .iter()
+            .any(|&c| c.is_alphanumeric() && self.alphanumeric_index(c).is_none())
+        {
+            return None;
+        }
+        if self.separately(&chars) {
+            let mut changed = false;
+            for alphabet in &self.alphabets {
+                if let Some(result) = alphabet.crypt(chars.clone(), direction) {
+                    chars = result;
+                    changed = true;
+                }
+            }
+            return changed.then(|| chars.into_iter().collect());
+        }
+        let count = chars
+            .iter()
+            .filter(|&&c| self.alphanumeric_index(c).is_some())
+            .count();
+        if count > MAX_TOGETHER {
+            return None;
+        }
+        // cycle walking: the result is encrypted again until it can't be taken for
+        // separately encrypted alphabets, decryption walks the same cycle back
+        loop {
+            chars = self.together(chars, direction)?;
+            if !self.separately(&chars) {
+                return Some(chars.into_iter().collect());
+            }
+        }
+    }
+
+    // checks whether every alphabet of the text is long enough to be encrypted on its own
+    fn separately(&self, chars: &[char]) -> bool {
+        self.alphabets.iter().all(|alphabet| {
+            let count = chars
+                .iter()
+                .filter(|&&c| alphabet.index(c).is_some())
+                .count();
+            count == 0 || count >= alphabet.min_len()
+        })
+    }
+
+    fn together(&self, mut chars: Vec<char>, direction: Direction) -> Option<Vec<char>> {
+        let positions: Vec<usize> = (0..chars.len())
+            .filter(|&i| self.alphanumeric_index(chars[i]).is_some())
+            .collect();
+        let numerals: Vec<u16> = positions
+            .iter()
+            .filter_map(|&i| self.alphanumeric_index(chars[i]))
+            .collect();
+        let numerals = FlexibleNumeralString::from(numerals);
+        let result = match direction {
+            Direction::Encrypt => self.alphanumeric.encrypt(&[], &numerals),
+            Direction::Decrypt => self.alphanumeric.decrypt(&[], &numerals),
+        }
+        .ok()?;
+        for (&i, numeral) in positions.iter().zip(Vec::<u16>::from(result)) {
+            chars[i] = self.alphanumeric_char(numeral);
+        }
+        Some(chars)
+    }
+
+    // digits, then lowercase and uppercase letters
+    fn alphanumeric_index(&self, c: char) -> Option<u16> {
+        let mut offset = 0;
+        for alphabet in &self.alphabets {
+            if let Some(index) = alphabet.index(c) {
+                return Some(offset + index);
+            }
+            offset += alphabet.radix as u16;
+        }
+        None
+    }
+
+    fn alphanumeric_char(&self, mut index: u16) -> char {
+        for alphabet in &self.alphabets {
+            if index < alphabet.radix as u16 {
+                return alphabet.char(index);
+            }
+            index -= alphabet.radix as u16;
+        }
+        unreachable!("index is in one of the alphabets")
+    }
+}
+
+impl Alphabet {
+    fn index(&self, c: char) -> Option<u16> {
+        let index = (c as u32).checked_sub(self.first as u32)?;
+        (index < self.radix).then_some(index as u16)
+    }
+
+    fn char(&self, index: u16) -> char {
+        char::from_u32(self.first as u32 + index as u32).expect("index is in the alphabet")
+    }
+
+    // fewest characters that give FF1 enough possible values
+    fn min_len(&self) -> usize {
+        let mut len = 1;
+        while (self.radix as u64).pow(len) < MIN_DOMAIN {
+            len += 1;
+        }
+        len as usize
+    }
+
+    // encrypts characters of this alphabet keeping their positions, `None` if there are
+    // too few of them
+    fn crypt(&self, mut chars: Vec<char>, direction: Direction) -> Option<Vec<char>> {
+        let positions: Vec<usize> = (0..chars.len())
+            .filter(|&i| self.index(chars[i]).is_some())
+            .collect();
+        let numerals: Vec<u16> = positions
+            .iter()
+            .filter_map(|&i| self.index(chars[i]))
+            .collect();
+        let numerals = FlexibleNumeralString::from(numerals);
+        // the alphabet is the tweak, so equal digits and letters are encrypted differently
+        let tweak = [self.first as u8];
+        let result = match direction {
+            Direction::Encrypt => self.cipher.encrypt(&tweak, &numerals),
+            Direction::Decrypt => self.cipher.decrypt(&tweak, &numerals),
+        }
+        .ok()?;
+        for (&i, numeral) in positions.iter().zip(Vec::<u16>::from(result)) {
+            chars[i] = self.char(numeral);
+        }
+        Some(chars)
+    }
+}
+
+// number written exactly as the text, so it's written the same way in the output
+fn number_of(text: &str) -> Option<Number> {
+    serde_json::from_str::<Number>(text)
+        .ok()
+        .filter(|number| number.to_string() == text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn encryptor(secret: &str) -> Encryptor {
+        Encryptor::new(secret.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_format_is_kept() {
+        let encryptor = encryptor("secret");
+        let encrypted = encryptor.encrypt(&json!("4111-1111-1111-1111")).unwrap();
+        let encrypted = encrypted.as_str().unwrap();
+        assert_ne!(encrypted, "4111-1111-1111-1111");
+        assert_eq!(encrypted.len(), 19);
+        assert!(encrypted.chars().enumerate().all(|(i, c)| if i % 5 == 4 {
+            c == '-'
+        } else {
+            c.is_ascii_digit()
+        }));
+
+        let encrypted = encryptor.encrypt(&json!("Jon Snow, ACC-004213")).unwrap();
+        let encrypted = encrypted.as_str().unwrap();
+        let shape = |text: &str| -> String {
+            text.chars()
+                .map(|c| match c {
+                    '0'..='9' => '9',
+                    'a'..='z' => 'a',
+                    'A'..='Z' => 'A',
+                    c => c,
+                })
+                .collect()
+        };
+        assert_eq!(shape(encrypted), shape("Jon Snow, ACC-004213"));
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let encryptor = encryptor("secret");
+        for value in [
+            json!("4111111111111111"),
+            json!("Jon Snow, ACC-004213"),
+            json!("Jon Snow"),
+            json!("AB 123456 C"),
+            json!(1234567890),
+            json!(-9876543210i64),
+            json!(18446744073709551615u64),
+            json!(1234.5678),
+            json!(-0.000123456),
+        ] {
+            let encrypted = encryptor.encrypt(&value).unwrap();
+            assert_ne!(encrypted, value);
+            assert_eq!(encryptor.decrypt(&encrypted), value);
+        }
+        let nested = json!({"ids": ["ABCDEFGH", 12345678]});
+        assert_eq!(
+            encryptor.decrypt(&json!({"ids": [
+                encryptor.encrypt(&nested["ids"][0]).unwrap(),
+                encryptor.encrypt(&nested["ids"][1]).unwrap(),
+            ]})),
+            nested
+        );
+    }
+
+    #[test]
+    fn test_numbers_stay_numbers() {
+        let encryptor = encryptor("secret");
+        for value in [1000000, 5550123456i64] {
+            let encrypted = encryptor.encrypt(&json!(value)).unwrap();
+            assert!(encrypted.is_i64());
+            assert_eq!(encrypted.to_string().len(), value.to_string().len());
+        }
+        let encrypted = encryptor.encrypt(&json!(1234.5678)).unwrap();
+        assert!(encrypted.is_f64());
+        assert_eq!(encrypted.to_string().len(), 9);
+        assert!(encryptor.encrypt(&json!(42)).is_err());
+        assert!(encryptor.encrypt(&json!(4.25)).is_err());
+    }
+
+    #[test]
+    fn test_short_alphabets_are_not_kept() {
+        let encryptor = encryptor("secret");
+        for value in ["Jon Snow", "AB 123456 C", "Order ab12 Ref X9"] {
+            let encrypted = encryptor.encrypt(&json!(value)).unwrap();
+            let encrypted = encrypted.as_str().unwrap();
+            assert_eq!(encrypted.len(), value.len());
+            // every word is changed, not only the long enough kinds of characters
+            for (word, encrypted_word) in value.split(' ').zip(encrypted.split(' ')) {
+                assert_ne!(word, encrypted_word, "{} -> {}", value, encrypted);
+                assert!(encrypted_word.chars().all(|c| c.is_ascii_alphanumeric()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_values_are_masked() {
+        let encryptor = encryptor("secret");
+        assert_eq!(encryptor.encrypt(&json!("42")).unwrap(), json!("**"));
+        assert_eq!(encryptor.encrypt(&json!("A-1")).unwrap(), json!("*-*"));
+        assert_eq!(encryptor.decrypt(&json!("**")), json!("**"));
+        // too long to mix all digits and letters, too few uppercase letters on their own
+        let text = format!("A{}", "b".repeat(70));
+        let masked = encryptor.encrypt(&json!(text)).unwrap();
+        assert_eq!(masked, json!("*".repeat(71)));
+    }
+
+    #[test]
+    fn test_other_scripts_are_masked() {
+        let encryptor = encryptor("secret");
+        let value = json!("Олександр Ivanenko 1234567");
+        let masked = encryptor.encrypt(&value).unwrap();
+        assert_eq!(masked, json!("********* ******** *******"));
+        assert_eq!(encryptor.decrypt(&value), value);
+    }
+
+    #[test]
+    fn test_depends_on_secret() {
+        let value = json!("4111111111111111");
+        assert_eq!(
+            encryptor("a").encrypt(&value).unwrap(),
+            encryptor("a").encrypt(&value).unwrap()
+        );
+        assert_ne!(
+            encryptor("a").encrypt(&value).unwrap(),
+            encryptor("b").encrypt(&value).unwrap()
+        );
+        assert!(Encryptor::new(b"").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::fs;
+use std::path::Path;
+
+/// environment variable with the secret used for keyed hashing
+pub const SECRET_ENV: &str = "HIDE_HASH_SECRET";
+
+const DEFAULT_LENGTH: usize = 16;
+const MAX_LENGTH: usize = 64;
+
+/// Keyed hashing (HMAC-SHA256), the same value hashed with the same secret always gives
+/// the same result, so hashed ids can still be joined across files.
+#[derive(Clone)]
+pub struct Hasher {
+    secret: Vec<u8>,
+    length: usize,
+    prefix: String,
+}
+
+impl Hasher {
+    pub fn new(secret: Vec<u8>, length: Option<usize>, prefix: Option<String>) -> Result<Self> {
+        let length = length.unwrap_or(DEFAULT_LENGTH);
+        if length == 0 || length > MAX_LENGTH {
+            bail!(
+                "hash length must be between 1 and {}, got {}",
+                MAX_LENGTH,
+                length
+            );
+        }
+        if secret.is_empty() {
+            bail!("hash secret can't be empty");
+        }
+        Ok(Hasher {
+            secret,
+            length,
+            prefix: prefix.unwrap_or_default(),
+        })
+    }
+
+    /// Reads the secret from the key file if given, otherwise from `HIDE_HASH_SECRET`.
+    /// Returns `None` if neither is set.
+    pub fn load_secret(key_file: Option<&Path>) -> Result<Option<Vec<u8>>> {
+        if let Some(path) = key_file {
+            let secret = fs::read(path)
+                .with_context(|| format!("could not read hash key file: {}", path.display()))?;
+            return Ok(Some(secret.trim_ascii_end().to_vec()));
+        }
+        Ok(std::env::var(SECRET_ENV).ok().map(String::into_bytes))
+    }
+
+    /// Hashes strings and numbers, returns `None` for other values.
+    pub fn hash(&self, value: &Value) -> Option<Value> {
+        let text = match value {
+            Value::String(text) => text.to_owned(),
+            Value::Number(number) => number.to_string(),
+            _ => return None,
+        };
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC can take key of any size");
+        mac.update(text.as_bytes());
+        let digest = hex::encode(mac.finalize().into_bytes());
+        Some(Value::String(format!(
+            "{}{}",
+            self.prefix,
+            &digest[..self.length]
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn hasher(secret: &str) -> Hasher {
+        Hasher::new(
+            secret.as_bytes().to_vec(),
+            Some(12),
+            Some(String::from("id_")),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_same_value_same_hash() {
+        let hasher = hasher("secret");
+        assert_eq!(hasher.hash(&json!("42")), hasher.hash(&json!("42")));
+        assert_ne!(hasher.hash(&json!("42")), hasher.hash(&json!("43")));
+    }
+
+    #[test]
+    fn test_number_hashed_as_its_text() {
+        let hasher = hasher("secret");
+        assert_eq!(hasher.hash(&json!(42)), hasher.hash(&json!("42")));
+    }
+
+    #[test]
+    fn test_secret_changes_hash() {
+        assert_ne!(
+            hasher("one").hash(&json!("42")),
+            hasher("two").hash(&json!("42"))
+        );
+    }
+
+    #[test]
+    fn test_length_and_prefix() {
+        let hashed = hasher("secret").hash(&json!("42")).unwrap();
+        let hashed = hashed.as_str().unwrap();
+        assert!(hashed.starts_with("id_"));
+        assert_eq!(hashed.len(), 15);
+    }
+
+    #[test]
+    fn test_bool_not_hashed() {
+        assert_eq!(hasher("secret").hash(&json!(true)), None);
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        assert!(Hasher::new(b"secret".to_vec(), Some(0), None).is_err());
+        assert!(Hasher::new(b"secret".to_vec(), Some(65), None).is_err());
+    }
+}
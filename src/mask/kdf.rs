@@ -0,0 +1,29 @@
+use anyhow::{anyhow, Result};
+use argon2::Argon2;
+
+/// Length of the keys derived from secrets, enough for AES-256.
+pub const KEY_LEN: usize = 32;
+
+/// Derives a key from a user secret with Argon2id, so guessing the secret offline is slow.
+/// The salt has to be at least 8 bytes long.
+pub fn derive_key(secret: &[u8], salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+    let mut key = [0; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|error| anyhow!("could not derive key from the secret: {}", error))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_derive_key() {
+        let key = derive_key(b"secret", b"salt of the key").unwrap();
+        assert_eq!(key, derive_key(b"secret", b"salt of the key").unwrap());
+        assert_ne!(key, derive_key(b"secret", b"another salt").unwrap());
+        assert_ne!(key, derive_key(b"secret2", b"salt of the key").unwrap());
+        assert!(derive_key(b"secret", b"short").is_err());
+    }
+}
@@ -0,0 +1,835 @@
+use self::fpe::Encryptor;
+use crate::detect::Detectors;
+use crate::keys_storage::{self, InMemoryKeysStorage, JsonPath, KeysStorage};
+use anyhow::{bail, Context, Result};
+use faker::Faker;
+use hash::Hasher;
+use numeric::NumericMask;
+use partial::PartialMask;
+use pseudonym::Pseudonymizer;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub mod faker;
+pub mod fpe;
+pub mod hash;
+mod kdf;
+pub mod numeric;
+pub mod partial;
+pub mod pseudonym;
+pub mod vault;
+
+use vault::Vault;
+
+/// Defines how the value of a sensitive key is hidden.
+///
+/// Written in config and command line as `placeholder`, `null`, `remove`, `partial`, `hash`,
+/// `token`, `fpe`, `fake`, `faker`, `pseudonym` or `literal:<text>`. Partial masking takes options
+/// after a colon, see [`PartialMask`]: `partial:first=2,last=2` or `partial:email`. Numbers can
+/// be generalized with `bucket:<size>`, `round:<step>`, `truncate:<decimals>`, `noise:<bound>`
+/// or `laplace:<epsilon>[,<sensitivity>]`, see [`NumericMask`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(try_from = "String", into = "String")]
+pub enum MaskStrategy {
+    /// replaces value with its type name: "String", "Number" or "Bool"
+    #[default]
+    Placeholder,
+    /// replaces value with the given text
+    Literal(String),
+    /// replaces value with null
+    Null,
+    /// removes the key from the output
+    Remove,
+    /// keeps the first and the last characters of the value visible, masks the rest
+    Partial(PartialMask),
+    /// generalizes numbers or adds noise to them, keeping them numbers
+    Numeric(NumericMask),
+    /// replaces strings and numbers with their keyed hash
+    Hash,
+    /// replaces value with a token, original value is kept in the vault
+    Token,
+    /// encrypts value keeping its length and format, digits stay digits
+    Fpe,
+    /// replaces value with random data of the same shape
+    Fake,
+    /// replaces value with plausible data of the same kind: names, emails, dates...
+    Faker,
+    /// replaces value with a pseudonym like `user_1`, the same for the same values
+    Pseudonym,
+}
+
+impl FromStr for MaskStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(text) = s.strip_prefix("literal:") {
+            return Ok(MaskStrategy::Literal(text.to_owned()));
+        }
+        if let Some(options) = s.strip_prefix("partial:") {
+            return Ok(MaskStrategy::Partial(options.parse()?));
+        }
+        if let Some((name, _)) = s.split_once(':') {
+            if numeric::NAMES.contains(&name) {
+                return Ok(MaskStrategy::Numeric(s.parse()?));
+            }
+        }
+        match s {
+            "placeholder" => Ok(MaskStrategy::Placeholder),
+            "null" => Ok(MaskStrategy::Null),
+            "remove" => Ok(MaskStrategy::Remove),
+            "partial" => Ok(MaskStrategy::Partial(PartialMask::default())),
+            "hash" => Ok(MaskStrategy::Hash),
+            "token" => Ok(MaskStrategy::Token),
+            "fpe" => Ok(MaskStrategy::Fpe),
+            "fake" => Ok(MaskStrategy::Fake),
+            "faker" => Ok(MaskStrategy::Faker),
+            "pseudonym" => Ok(MaskStrategy::Pseudonym),
+            _ => Err(format!(
+                "unknown strategy '{}', expected one of: placeholder, null, remove, partial[:<options>], hash, token, fpe, fake, faker, pseudonym, literal:<text>, bucket:<size>, round:<step>, truncate:<decimals>, noise:<bound>, laplace:<epsilon>",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MaskStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaskStrategy::Placeholder => write!(f, "placeholder"),
+            MaskStrategy::Literal(text) => write!(f, "literal:{}", text),
+            MaskStrategy::Null => write!(f, "null"),
+            MaskStrategy::Remove => write!(f, "remove"),
+            MaskStrategy::Partial(options) if *options == PartialMask::default() => {
+                write!(f, "partial")
+            }
+            MaskStrategy::Partial(options) => write!(f, "partial:{}", options),
+            MaskStrategy::Numeric(numeric) => write!(f, "{}", numeric),
+            MaskStrategy::Hash => write!(f, "hash"),
+            MaskStrategy::Token => write!(f, "token"),
+            MaskStrategy::Fpe => write!(f, "fpe"),
+            MaskStrategy::Fake => write!(f, "fake"),
+            MaskStrategy::Faker => write!(f, "faker"),
+            MaskStrategy::Pseudonym => write!(f, "pseudonym"),
+        }
+    }
+}
+
+impl TryFrom<String> for MaskStrategy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<MaskStrategy> for String {
+    fn from(value: MaskStrategy) -> Self {
+        value.to_string()
+    }
+}
+
+/// Transformation applied to the values while walking through the JSON tree.
+pub trait Transform {
+    /// Called for values of sensitive keys, returns `None` if the key should be removed.
+    fn hide(&mut self, value: &Value, path: &JsonPath) -> Option<Value>;
+    /// Called for every other primitive value, returns `None` if the key should be removed.
+    fn inspect(&mut self, value: &Value, path: &JsonPath) -> Option<Value>;
+    /// Fails if some value couldn't be hidden the way it was asked, then the output
+    /// must not be used.
+    fn check(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Picks a strategy for every hidden key: the one configured for the key, or the default one.
+/// Keys of strategies can be literal keys, patterns and path rules, like sensitive keys.
+pub struct Masker {
+    default_strategy: MaskStrategy,
+    key_strategies: HashMap<String, MaskStrategy>,
+    // every key of a strategy compiled on its own, sorted by key so the first match
+    // is always the same
+    rules: Vec<(InMemoryKeysStorage, MaskStrategy)>,
+    hasher: Option<Hasher>,
+    vault: Option<Vault>,
+    // shared with workers, the cipher can't be cloned
+    encryptor: Option<Arc<Encryptor>>,
+    faker: Option<Faker>,
+    pseudonymizer: Option<Pseudonymizer>,
+    detectors: Option<Detectors>,
+    // number of values hidden since the last `take_masked`
+    masked: usize,
+    // the first value that couldn't be hidden, reported by `check`
+    failure: Option<anyhow::Error>,
+}
+
+impl Masker {
+    pub fn new(
+        default_strategy: MaskStrategy,
+        key_strategies: HashMap<String, MaskStrategy>,
+    ) -> Result<Self> {
+        let mut keys: Vec<&String> = key_strategies.keys().collect();
+        keys.sort();
+        let rules = keys
+            .into_iter()
+            .map(|key| {
+                let storage = InMemoryKeysStorage::init_with(&HashSet::from([key.to_owned()]))
+                    .with_context(|| format!("invalid key of a strategy: {}", key))?;
+                Ok((storage, key_strategies[key].clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Masker {
+            default_strategy,
+            key_strategies,
+            rules,
+            hasher: None,
+            vault: None,
+            encryptor: None,
+            faker: None,
+            pseudonymizer: None,
+            detectors: None,
+            masked: 0,
+            failure: None,
+        })
+    }
+
+    /// Looks up key strategies ignoring case and separators, the same way keys storage does.
+    /// Fails if keys which become the same have different strategies, like `phone_number`
+    /// and `phoneNumber`, as it's not clear which one to use.
+    pub fn normalize_keys(mut self, normalize: bool) -> Result<Self> {
+        if normalize {
+            let mut normalized: HashMap<String, &String> = HashMap::new();
+            let mut keys: Vec<&String> = self.key_strategies.keys().collect();
+            keys.sort();
+            for key in keys.into_iter().filter(|key| keys_storage::is_literal(key)) {
+                match normalized.insert(keys_storage::normalize_key(key), key) {
+                    Some(other) if self.key_strategies[other] != self.key_strategies[key] => {
+                        bail!(
+                            "keys '{}' and '{}' have different strategies but are the same when normalized",
+                            other,
+                            key
+                        )
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.rules = self
+            .rules
+            .into_iter()
+            .map(|(storage, strategy)| (storage.normalize_keys(normalize), strategy))
+            .collect();
+        Ok(self)
+    }
+
+    pub fn with_hasher(mut self, hasher: Hasher) -> Self {
+        self.hasher = Some(hasher);
+        self
+    }
+
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    pub fn with_encryptor(mut self, encryptor: Encryptor) -> Self {
+        self.encryptor = Some(Arc::new(encryptor));
+        self
+    }
+
+    pub fn with_faker(mut self, faker: Faker) -> Self {
+        self.faker = Some(faker);
+        self
+    }
+
+    pub fn with_pseudonymizer(mut self, pseudonymizer: Pseudonymizer) -> Self {
+        self.pseudonymizer = Some(pseudonymizer);
+        self
+    }
+
+    pub fn with_detectors(mut self, detectors: Detectors) -> Self {
+        self.detectors = Some(detectors);
+        self
+    }
+
+    /// Checks whether masked values depend on the values masked before, like tokens
+    /// in the vault or pseudonyms, so one masker has to see all the input.
+    pub fn keeps_state(&self) -> bool {
+        self.vault.is_some() || self.pseudonymizer.is_some()
+    }
+
+    /// Masker for another thread with the same strategies, secrets and detectors, and its own
+    /// counters. Tokens and pseudonyms have to be the same in every thread, so a masker that
+    /// keeps state has no workers.
+    pub fn worker(&self) -> Masker {
+        assert!(
+            !self.keeps_state(),
+            "masker with a vault or pseudonyms is not shared"
+        );
+        Masker {
+            default_strategy: self.default_strategy.clone(),
+            key_strategies: self.key_strategies.clone(),
+            rules: self.rules.clone(),
+            hasher: self.hasher.clone(),
+            vault: None,
+            encryptor: self.encryptor.clone(),
+            faker: self.faker.clone(),
+            pseudonymizer: None,
+            detectors: self.detectors.clone(),
+            masked: 0,
+            failure: None,
+        }
+    }
+
+    /// Saves tokens to the vault and pseudonyms to their map file.
+    pub fn save(&self) -> Result<()> {
+        if let Some(vault) = &self.vault {
+            vault.save()?;
+        }
+        if let Some(pseudonymizer) = &self.pseudonymizer {
+            pseudonymizer.save()?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether the strategy is used as the default one or for any key.
+    pub fn uses(&self, strategy: &MaskStrategy) -> bool {
+        &self.default_strategy == strategy || self.key_strategies.values().any(|s| s == strategy)
+    }
+
+    /// Returns how many values were hidden since the previous call.
+    pub fn take_masked(&mut self) -> usize {
+        std::mem::take(&mut self.masked)
+    }
+
+    /// Picks the strategy for the value at the path: the one of exactly the same key,
+    /// then the first one whose key matches, then the default one.
+    pub fn strategy_for(&self, path: &JsonPath) -> &MaskStrategy {
+        let key = path.last_key().unwrap_or_default();
+        if let Some(strategy) = self.key_strategies.get(key) {
+            return strategy;
+        }
+        self.rules
+            .iter()
+            .find(|(storage, _)| storage.contains(key) || storage.contains_path(path))
+            .map_or(&self.default_strategy, |(_, strategy)| strategy)
+    }
+
+    /// Decrypts the value if the key was hidden with format-preserving encryption,
+    /// other values are returned as they are.
+    pub fn decrypt(&self, value: &Value, path: &JsonPath) -> Value {
+        match (self.strategy_for(path), &self.encryptor) {
+            (MaskStrategy::Fpe, Some(encryptor)) => encryptor.decrypt(value),
+            _ => value.clone(),
+        }
+    }
+
+    /// Returns hidden value, or `None` if the key should be removed.
+    /// The key helps some strategies to pick the kind of the replacement.
+    pub fn apply(&mut self, strategy: &MaskStrategy, key: &str, value: &Value) -> Option<Value> {
+        match strategy {
+            MaskStrategy::Remove => None,
+            MaskStrategy::Null => Some(Value::Null),
+            _ => Some(self.apply_to_leaves(strategy, key, value)),
+        }
+    }
+
+    // keeps arrays and objects in place, so the shape of the value is not lost
+    fn apply_to_leaves(&mut self, strategy: &MaskStrategy, key: &str, value: &Value) -> Value {
+        match value {
+            Value::Null => Value::Null,
+            Value::Array(values) => Value::Array(
+                values
+                    .iter()
+                    .map(|v| self.apply_to_leaves(strategy, key, v))
+                    .collect(),
+            ),
+            Value::Object(values) => Value::Object(
+                values
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), self.apply_to_leaves(strategy, k, v)))
+                    .collect(),
+            ),
+            leaf => match strategy {
+                MaskStrategy::Placeholder => placeholder(leaf),
+                MaskStrategy::Literal(text) => Value::String(text.to_owned()),
+                MaskStrategy::Partial(options) => options.apply(leaf),
+                MaskStrategy::Numeric(numeric) => match numeric.apply(leaf) {
+                    Ok(masked) => masked,
+                    Err(error) => self.fail(error, key, leaf),
+                },
+                MaskStrategy::Hash => self
+                    .hasher
+                    .as_ref()
+                    .and_then(|hasher| hasher.hash(leaf))
+                    .unwrap_or_else(|| placeholder(leaf)),
+                MaskStrategy::Token => match self.vault.as_mut() {
+                    Some(vault) => Value::String(vault.tokenize(leaf)),
+                    None => placeholder(leaf),
+                },
+                MaskStrategy::Fpe => match self.encryptor.as_ref().map(|e| e.encrypt(leaf)) {
+                    Some(Ok(encrypted)) => encrypted,
+                    Some(Err(error)) => self.fail(error, key, leaf),
+                    None => placeholder(leaf),
+                },
+                MaskStrategy::Fake => fake(leaf),
+                MaskStrategy::Pseudonym => match self.pseudonymizer.as_mut() {
+                    Some(pseudonymizer) => pseudonymizer.pseudonymize(key, leaf),
+                    None => placeholder(leaf),
+                },
+                MaskStrategy::Faker => match self.faker.as_ref() {
+                    Some(faker) => faker.fake(key, leaf),
+                    None => fake(leaf),
+                },
+                MaskStrategy::Null | MaskStrategy::Remove => Value::Null,
+            },
+        }
+    }
+
+    // remembers the first failure for `check`, the value is replaced with a placeholder
+    fn fail(&mut self, error: anyhow::Error, key: &str, value: &Value) -> Value {
+        let error = error.context(format!("could not hide value of '{}'", key));
+        self.failure.get_or_insert(error);
+        placeholder(value)
+    }
+}
+
+impl Transform for Masker {
+    fn hide(&mut self, value: &Value, path: &JsonPath) -> Option<Value> {
+        self.masked += 1;
+        let strategy = self.strategy_for(path).clone();
+        // array items have no key, so the closest key stands for them
+        self.apply(&strategy, path.last_key().unwrap_or_default(), value)
+    }
+
+    fn inspect(&mut self, value: &Value, path: &JsonPath) -> Option<Value> {
+        let (Some(detectors), Value::String(text)) = (&self.detectors, value) else {
+            return Some(value.clone());
+        };
+        let substrings = detectors.substrings;
+        let detections = detectors.scan(text);
+        if detections.is_empty() {
+            return Some(value.clone());
+        }
+        for detection in &detections {
+            log::debug!("{} detected at {}", detection.detector, path);
+        }
+        self.masked += 1;
+        let strategy = self.default_strategy.clone();
+        let key = path.last_key().unwrap_or_default();
+        if !substrings {
+            return self.apply(&strategy, key, value);
+        }
+        let mut masked = String::with_capacity(text.len());
+        let mut last = 0;
+        for detection in detections {
+            masked.push_str(&text[last..detection.range.start]);
+            let part = Value::String(text[detection.range.clone()].to_owned());
+            match self.apply(&strategy, key, &part) {
+                Some(Value::String(part)) => masked.push_str(&part),
+                // removed or nulled parts just disappear from the text
+                Some(Value::Null) | None => {}
+                Some(part) => masked.push_str(&part.to_string()),
+            }
+            last = detection.range.end;
+        }
+        masked.push_str(&text[last..]);
+        Some(Value::String(masked))
+    }
+
+    fn check(&mut self) -> Result<()> {
+        match self.failure.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Puts original values back in place of the tokens created by the token strategy
+/// and decrypts values hidden with format-preserving encryption.
+pub struct Restorer {
+    vault: Option<Vault>,
+    // knows which keys were encrypted and how to decrypt them
+    masker: Masker,
+}
+
+impl Restorer {
+    pub fn new(vault: Option<Vault>, masker: Masker) -> Self {
+        Restorer { vault, masker }
+    }
+
+    fn restore(&self, value: &Value) -> Value {
+        let Some(vault) = &self.vault else {
+            return value.clone();
+        };
+        match value {
+            Value::String(text) => vault.restore(text).unwrap_or_else(|| value.clone()),
+            Value::Array(values) => Value::Array(values.iter().map(|v| self.restore(v)).collect()),
+            Value::Object(values) => Value::Object(
+                values
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), self.restore(v)))
+                    .collect(),
+            ),
+            _ => value.clone(),
+        }
+    }
+}
+
+impl Transform for Restorer {
+    fn hide(&mut self, value: &Value, path: &JsonPath) -> Option<Value> {
+        let value = self.masker.decrypt(value, path);
+        Some(self.restore(&value))
+    }
+
+    fn inspect(&mut self, value: &Value, _path: &JsonPath) -> Option<Value> {
+        Some(self.restore(value))
+    }
+}
+
+fn placeholder(value: &Value) -> Value {
+    match value {
+        Value::Bool(_) => Value::String(String::from("Bool")),
+        Value::Number(_) => Value::String(String::from("Number")),
+        Value::String(_) => Value::String(String::from("String")),
+        _ => value.clone(),
+    }
+}
+
+fn fake(value: &Value) -> Value {
+    let mut rng = rand::thread_rng();
+    match value {
+        Value::Bool(_) => Value::Bool(rng.gen()),
+        Value::String(text) => Value::String(fake_chars(&mut rng, text)),
+        Value::Number(_) => fake_number(&mut rng, value),
+        _ => value.clone(),
+    }
+}
+
+fn fake_number(rng: &mut impl Rng, value: &Value) -> Value {
+    match value {
+        Value::Number(number) => {
+            // randomize digits only, so sign, decimal point and exponent stay in place
+            let mut digits: String = number
+                .to_string()
+                .chars()
+                .map(|c| match c.is_ascii_digit() {
+                    true => rng.gen_range(b'0'..=b'9') as char,
+                    false => c,
+                })
+                .collect();
+            let first = digits.find(|c: char| c.is_ascii_digit()).unwrap_or(0);
+            if digits[first..].starts_with('0')
+                && digits[first + 1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                digits.replace_range(first..first + 1, "1");
+            }
+            digits
+                .parse::<Number>()
+                .map(Value::Number)
+                .unwrap_or_else(|_| placeholder(value))
+        }
+        _ => value.clone(),
+    }
+}
+
+fn fake_chars(rng: &mut impl Rng, text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_digit() {
+                rng.gen_range(b'0'..=b'9') as char
+            } else if c.is_ascii_lowercase() {
+                rng.gen_range(b'a'..=b'z') as char
+            } else if c.is_ascii_uppercase() {
+                rng.gen_range(b'A'..=b'Z') as char
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::detect::Detector;
+    use crate::keys_storage::PathSegment;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!("null".parse(), Ok(MaskStrategy::Null));
+        assert_eq!(
+            "literal:***".parse(),
+            Ok(MaskStrategy::Literal(String::from("***")))
+        );
+        assert!("unknown".parse::<MaskStrategy>().is_err());
+        assert_eq!(
+            "round:10000".parse(),
+            Ok(MaskStrategy::Numeric(NumericMask::Round(10000.0)))
+        );
+        assert!("round:often".parse::<MaskStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_strategy_to_string_and_back() {
+        let strategy = MaskStrategy::Literal(String::from("hidden"));
+        assert_eq!(strategy.to_string().parse(), Ok(strategy));
+    }
+
+    fn key_path(keys: &[&str]) -> JsonPath {
+        let mut path = JsonPath::new();
+        for key in keys {
+            path.push(PathSegment::Key(key.to_string()));
+        }
+        path
+    }
+
+    fn masker() -> Masker {
+        Masker::new(MaskStrategy::Placeholder, HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn test_placeholder_keeps_shape() {
+        let value = json!({"a": [1, "b", true, null]});
+        let expected = json!({"a": ["Number", "String", "Bool", null]});
+        assert_eq!(
+            masker().apply(&MaskStrategy::Placeholder, "", &value),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn test_remove_and_null() {
+        let value = json!({"a": 1});
+        assert_eq!(masker().apply(&MaskStrategy::Remove, "", &value), None);
+        assert_eq!(
+            masker().apply(&MaskStrategy::Null, "", &value),
+            Some(Value::Null)
+        );
+    }
+
+    #[test]
+    fn test_partial() {
+        assert_eq!(
+            PartialMask::default().apply(&json!("4111111111111111")),
+            json!("************1111")
+        );
+        assert_eq!(PartialMask::default().apply(&json!("abc")), json!("***"));
+    }
+
+    #[test]
+    fn test_hash_uses_hasher() {
+        let hasher = Hasher::new(b"secret".to_vec(), None, Some(String::from("h_"))).unwrap();
+        let mut masker = masker().with_hasher(hasher);
+        let hashed = masker.apply(&MaskStrategy::Hash, "", &json!({"id": 42, "ok": true}));
+        let hashed = hashed.unwrap();
+        assert!(hashed["id"].as_str().unwrap().starts_with("h_"));
+        assert_eq!(hashed["ok"], json!("Bool"));
+    }
+
+    #[test]
+    fn test_worker_has_own_counter() {
+        let mut masker = Masker::new(
+            MaskStrategy::Placeholder,
+            HashMap::from([(String::from("id"), MaskStrategy::Null)]),
+        )
+        .unwrap();
+        masker.hide(&json!(1), &key_path(&["name"]));
+        let mut worker = masker.worker();
+        assert_eq!(
+            worker.hide(&json!(1), &key_path(&["id"])),
+            Some(Value::Null)
+        );
+        assert_eq!(worker.take_masked(), 1);
+        assert_eq!(masker.take_masked(), 1);
+    }
+
+    #[test]
+    fn test_masker_normalizes_key_strategy() {
+        let masker = Masker::new(
+            MaskStrategy::Placeholder,
+            HashMap::from([(String::from("phone_number"), MaskStrategy::Null)]),
+        )
+        .unwrap();
+        assert_eq!(
+            masker.strategy_for(&key_path(&["phoneNumber"])),
+            &MaskStrategy::Placeholder
+        );
+
+        let masker = masker.normalize_keys(true).unwrap();
+        assert_eq!(
+            masker.strategy_for(&key_path(&["phoneNumber"])),
+            &MaskStrategy::Null
+        );
+    }
+
+    #[test]
+    fn test_normalized_key_strategies_collide() {
+        let key_strategies = HashMap::from([
+            (String::from("phone_number"), MaskStrategy::Null),
+            (String::from("phoneNumber"), MaskStrategy::Remove),
+        ]);
+        let masker = Masker::new(MaskStrategy::Placeholder, key_strategies.clone()).unwrap();
+        assert!(masker.normalize_keys(true).is_err());
+
+        let masker = Masker::new(MaskStrategy::Placeholder, key_strategies)
+            .unwrap()
+            .normalize_keys(false)
+            .unwrap();
+        assert_eq!(
+            masker.strategy_for(&key_path(&["phoneNumber"])),
+            &MaskStrategy::Remove
+        );
+
+        // the same strategy is fine
+        let masker = Masker::new(
+            MaskStrategy::Placeholder,
+            HashMap::from([
+                (String::from("phone_number"), MaskStrategy::Null),
+                (String::from("PhoneNumber"), MaskStrategy::Null),
+            ]),
+        )
+        .unwrap();
+        assert!(masker.normalize_keys(true).is_ok());
+    }
+
+    #[test]
+    fn test_detected_value_masked() {
+        let mut masker = Masker::new(
+            MaskStrategy::Partial(PartialMask::default()),
+            HashMap::new(),
+        )
+        .unwrap()
+        .with_detectors(Detectors::new([Detector::Email], false));
+        let path = JsonPath::new();
+        assert_eq!(
+            masker.inspect(&json!("a@example.com"), &path),
+            Some(json!("*********.com"))
+        );
+        assert_eq!(masker.inspect(&json!("hello"), &path), Some(json!("hello")));
+        assert_eq!(masker.inspect(&json!(42), &path), Some(json!(42)));
+    }
+
+    #[test]
+    fn test_detected_substrings_masked() {
+        let mut masker = Masker::new(
+            MaskStrategy::Literal(String::from("<email>")),
+            HashMap::new(),
+        )
+        .unwrap()
+        .with_detectors(Detectors::new([Detector::Email], true));
+        assert_eq!(
+            masker.inspect(&json!("mail a@b.com or c@d.org"), &JsonPath::new()),
+            Some(json!("mail <email> or <email>"))
+        );
+    }
+
+    #[test]
+    fn test_token_restored() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let vault = Vault::open(&dir.path().join("vault"), b"secret").unwrap();
+        let mut masker = masker().with_vault(vault);
+        let value = json!({"id": 42, "tags": ["a", null]});
+
+        let tokenized = masker.apply(&MaskStrategy::Token, "", &value).unwrap();
+        assert!(tokenized["id"].as_str().unwrap().starts_with("tok_"));
+        assert_eq!(tokenized["tags"][1], Value::Null);
+
+        let vault = masker.vault.take();
+        let mut restorer = Restorer::new(
+            vault,
+            Masker::new(MaskStrategy::Token, HashMap::new()).unwrap(),
+        );
+        assert_eq!(restorer.inspect(&tokenized, &JsonPath::new()), Some(value));
+    }
+
+    #[test]
+    fn test_encrypted_restored() {
+        let key_strategies = HashMap::from([(String::from("account"), MaskStrategy::Fpe)]);
+        let new_masker = || {
+            Masker::new(MaskStrategy::Placeholder, key_strategies.clone())
+                .unwrap()
+                .with_encryptor(Encryptor::new(b"secret").unwrap())
+        };
+        let value = json!("UA213223130000026007233566001");
+        let encrypted = new_masker().hide(&value, &key_path(&["account"])).unwrap();
+        assert_ne!(encrypted, value);
+        assert_eq!(
+            encrypted.as_str().unwrap().len(),
+            value.as_str().unwrap().len()
+        );
+
+        let mut restorer = Restorer::new(None, new_masker());
+        assert_eq!(
+            restorer.hide(&encrypted, &key_path(&["account"])),
+            Some(value)
+        );
+        // other keys were not encrypted
+        assert_eq!(
+            restorer.hide(&encrypted, &key_path(&["name"])),
+            Some(encrypted.clone())
+        );
+    }
+
+    #[test]
+    fn test_fake_keeps_format() {
+        let faked = fake(&json!("Ab-12"));
+        let faked = faked.as_str().unwrap();
+        assert_eq!(faked.len(), 5);
+        assert!(faked.chars().next().unwrap().is_ascii_uppercase());
+        assert_eq!(faked.chars().nth(2), Some('-'));
+        assert!(fake(&json!(1234)).is_u64());
+    }
+
+    #[test]
+    fn test_masker_uses_key_strategy() {
+        let mut masker = Masker::new(
+            MaskStrategy::Placeholder,
+            HashMap::from([(String::from("email"), MaskStrategy::Null)]),
+        )
+        .unwrap();
+        assert_eq!(
+            masker.hide(&json!("a@b.c"), &key_path(&["email"])),
+            Some(Value::Null)
+        );
+        assert_eq!(
+            masker.hide(&json!("Jon"), &key_path(&["name"])),
+            Some(json!("String"))
+        );
+    }
+
+    #[test]
+    fn test_pattern_and_path_key_strategies() {
+        let masker = Masker::new(
+            MaskStrategy::Placeholder,
+            HashMap::from([
+                (String::from("*password*"), MaskStrategy::Null),
+                (String::from("$.user.name"), MaskStrategy::Remove),
+                (String::from("db_password"), MaskStrategy::Hash),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            masker.strategy_for(&key_path(&["smtp_password"])),
+            &MaskStrategy::Null
+        );
+        // an exact key wins over a pattern
+        assert_eq!(
+            masker.strategy_for(&key_path(&["db_password"])),
+            &MaskStrategy::Hash
+        );
+        assert_eq!(
+            masker.strategy_for(&key_path(&["user", "name"])),
+            &MaskStrategy::Remove
+        );
+        assert_eq!(
+            masker.strategy_for(&key_path(&["name"])),
+            &MaskStrategy::Placeholder
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use serde_json::{Number, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Names of the numeric strategies, written as `<name>:<parameter>`.
+pub const NAMES: [&str; 5] = ["bucket", "round", "truncate", "noise", "laplace"];
+
+// integers beyond this can't be kept exactly in f64
+const MAX_SAFE_INTEGER: f64 = 9007199254740992.0;
+
+/// Generalizes numbers or adds noise to them, so they stay numbers useful for analytics.
+/// Integers stay integers unless the result has a fraction, other values are replaced with
+/// a placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumericMask {
+    /// start of the range of the given size the number falls into: `bucket:10`, 37 -> 30
+    Bucket(f64),
+    /// nearest multiple of the step: `round:10000`, 52300 -> 50000
+    Round(f64),
+    /// number with the given count of decimals, the rest is cut off: `truncate:2`
+    Truncate(u32),
+    /// random noise up to the bound added: `noise:5`
+    Noise(f64),
+    /// random Laplace noise with scale sensitivity / epsilon added: `laplace:0.5` or
+    /// `laplace:0.5,1000`, sensitivity is 1 by default
+    Laplace { epsilon: f64, sensitivity: f64 },
+}
+
+impl NumericMask {
+    /// Fails if the result is too big for a JSON number, like with a tiny rounding step.
+    pub fn apply(&self, value: &Value) -> Result<Value> {
+        let Value::Number(number) = value else {
+            return Ok(super::placeholder(value));
+        };
+        let integer = number
+            .as_i64()
+            .map(i128::from)
+            .or(number.as_u64().map(i128::from));
+        if let Some(integer) = integer {
+            if let Some(result) = self.apply_to_integer(integer) {
+                return integer_number(result)
+                    .with_context(|| format!("{} gives a number too big for JSON", self));
+            }
+        }
+        let x = number.as_f64().expect("JSON numbers are finite");
+        let mut rng = rand::thread_rng();
+        let result = match self {
+            NumericMask::Bucket(size) => decimals_of(*size, (x / size).floor() * size),
+            NumericMask::Round(step) => decimals_of(*step, (x / step).round() * step),
+            // numbers this big have no fraction, so there is nothing to cut off
+            NumericMask::Truncate(_) if x.fract() == 0.0 => x,
+            NumericMask::Truncate(decimals) => {
+                let factor = 10f64.powi(*decimals as i32);
+                (x * factor).trunc() / factor
+            }
+            NumericMask::Noise(bound) => x + rng.gen_range(-bound..=*bound),
+            NumericMask::Laplace {
+                epsilon,
+                sensitivity,
+            } => x + laplace(&mut rng, sensitivity / epsilon),
+        };
+        // noise doesn't turn whole numbers into fractions
+        let result = match self {
+            NumericMask::Noise(_) | NumericMask::Laplace { .. } if integer.is_some() => {
+                result.round()
+            }
+            _ => result,
+        };
+        if integer.is_some() && result.fract() == 0.0 && result.abs() < MAX_SAFE_INTEGER {
+            return Ok(Value::Number(Number::from(result as i64)));
+        }
+        match Number::from_f64(result) {
+            Some(number) => Ok(Value::Number(number)),
+            None => bail!("{} gives a number too big for JSON", self),
+        }
+    }
+
+    // exact result for integers when the parameter is whole too, `None` otherwise
+    fn apply_to_integer(&self, x: i128) -> Option<i128> {
+        let whole = |parameter: f64| {
+            (parameter.fract() == 0.0 && parameter < MAX_SAFE_INTEGER).then_some(parameter as i128)
+        };
+        match self {
+            NumericMask::Bucket(size) => {
+                let size = whole(*size)?;
+                Some(x.div_euclid(size) * size)
+            }
+            NumericMask::Round(step) => {
+                // halves are rounded away from zero, like f64::round
+                let step = whole(*step)?;
+                let rounded = (x.abs() + step / 2) / step * step;
+                Some(rounded * x.signum())
+            }
+            NumericMask::Truncate(_) => Some(x),
+            NumericMask::Noise(bound) => {
+                let noise: f64 = rand::thread_rng().gen_range(-bound..=*bound);
+                Some(x.saturating_add(noise.round() as i128))
+            }
+            NumericMask::Laplace {
+                epsilon,
+                sensitivity,
+            } => {
+                let noise = laplace(&mut rand::thread_rng(), sensitivity / epsilon);
+                Some(x.saturating_add(noise.round() as i128))
+            }
+        }
+    }
+}
+
+fn integer_number(x: i128) -> Result<Value> {
+    let number = match i64::try_from(x) {
+        Ok(x) => Number::from(x),
+        Err(_) => Number::from(u64::try_from(x)?),
+    };
+    Ok(Value::Number(number))
+}
+
+// drops floating point errors like 0.30000000000000004, keeping as many decimals as the step
+fn decimals_of(step: f64, value: f64) -> f64 {
+    let decimals = step
+        .to_string()
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len());
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+// inverse of the Laplace distribution function
+fn laplace(rng: &mut impl Rng, scale: f64) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    if u == -0.5 {
+        return 0.0;
+    }
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+impl FromStr for NumericMask {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let Some((name, parameter)) = s.split_once(':') else {
+            return Err(format!("expected <name>:<parameter>, got '{}'", s));
+        };
+        let positive = |text: &str| match text.parse::<f64>() {
+            Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+            _ => Err(format!(
+                "{} expects a positive number, got '{}'",
+                name, text
+            )),
+        };
+        match name {
+            "bucket" => Ok(NumericMask::Bucket(positive(parameter)?)),
+            "round" => Ok(NumericMask::Round(positive(parameter)?)),
+            "truncate" => match parameter.parse::<u32>() {
+                Ok(decimals) if decimals <= 15 => Ok(NumericMask::Truncate(decimals)),
+                _ => Err(format!(
+                    "truncate expects decimals from 0 to 15, got '{}'",
+                    parameter
+                )),
+            },
+            "noise" => Ok(NumericMask::Noise(positive(parameter)?)),
+            "laplace" => {
+                let (epsilon, sensitivity) = match parameter.split_once(',') {
+                    Some((epsilon, sensitivity)) => (positive(epsilon)?, positive(sensitivity)?),
+                    None => (positive(parameter)?, 1.0),
+                };
+                Ok(NumericMask::Laplace {
+                    epsilon,
+                    sensitivity,
+                })
+            }
+            _ => Err(format!(
+                "unknown numeric strategy '{}', expected one of: {}",
+                name,
+                NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NumericMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumericMask::Bucket(size) => write!(f, "bucket:{}", size),
+            NumericMask::Round(step) => write!(f, "round:{}", step),
+            NumericMask::Truncate(decimals) => write!(f, "truncate:{}", decimals),
+            NumericMask::Noise(bound) => write!(f, "noise:{}", bound),
+            NumericMask::Laplace {
+                epsilon,
+                sensitivity,
+            } if *sensitivity == 1.0 => write!(f, "laplace:{}", epsilon),
+            NumericMask::Laplace {
+                epsilon,
+                sensitivity,
+            } => write!(f, "laplace:{},{}", epsilon, sensitivity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn mask(strategy: &str, value: Value) -> Value {
+        strategy
+            .parse::<NumericMask>()
+            .unwrap()
+            .apply(&value)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_generalization() {
+        assert_eq!(mask("bucket:10", json!(37)), json!(30));
+        assert_eq!(mask("bucket:10", json!(-3)), json!(-10));
+        assert_eq!(mask("bucket:0.5", json!(1.7)), json!(1.5));
+        assert_eq!(mask("round:10000", json!(52300)), json!(50000));
+        assert_eq!(mask("round:10000", json!(55000)), json!(60000));
+        assert_eq!(mask("round:0.1", json!(0.27)), json!(0.3));
+        assert_eq!(mask("round:2.5", json!(7)), json!(7.5));
+        assert_eq!(mask("truncate:2", json!(50.45678)), json!(50.45));
+        assert_eq!(mask("truncate:2", json!(-30.52999)), json!(-30.52));
+        assert_eq!(mask("truncate:0", json!(42)), json!(42));
+    }
+
+    #[test]
+    fn test_noise() {
+        for _ in 0..100 {
+            let noisy = mask("noise:5", json!(40));
+            let noisy = noisy.as_i64().unwrap();
+            assert!((35..=45).contains(&noisy));
+            let noisy = mask("noise:0.5", json!(1.25)).as_f64().unwrap();
+            assert!((0.75..=1.75).contains(&noisy));
+            assert!(mask("laplace:0.1,1000", json!(52300)).is_i64());
+            assert!(mask("laplace:1", json!(1.5)).is_f64());
+        }
+    }
+
+    #[test]
+    fn test_big_integers_stay_exact() {
+        assert_eq!(
+            mask("bucket:10", json!(9007199254740993u64)),
+            json!(9007199254740990u64)
+        );
+        assert_eq!(
+            mask("round:1000", json!(9007199254740993u64)),
+            json!(9007199254741000u64)
+        );
+        assert_eq!(mask("round:10", json!(-55)), json!(-60));
+        assert_eq!(
+            mask("truncate:2", json!(9223372036854775807i64)),
+            json!(9223372036854775807i64)
+        );
+        let noisy = mask("noise:1", json!(18446744073709551000u64));
+        let noisy = noisy.as_u64().unwrap();
+        assert!((18446744073709550999..=18446744073709551001).contains(&noisy));
+    }
+
+    #[test]
+    fn test_overflow_fails() {
+        assert_eq!(mask("truncate:15", json!(1e300)), json!(1e300));
+        let round = "round:1e-300".parse::<NumericMask>().unwrap();
+        assert!(round.apply(&json!(1e10)).is_err());
+        let round = "round:1000".parse::<NumericMask>().unwrap();
+        assert!(round.apply(&json!(18446744073709551615u64)).is_err());
+        let noise = "noise:1e300".parse::<NumericMask>().unwrap();
+        assert!(noise.apply(&json!(5)).is_err());
+    }
+
+    #[test]
+    fn test_other_values() {
+        assert_eq!(mask("round:10", json!("52300")), json!("String"));
+        assert_eq!(mask("round:10", json!(true)), json!("Bool"));
+    }
+
+    #[test]
+    fn test_parse() {
+        for strategy in [
+            "bucket:10",
+            "round:0.5",
+            "truncate:2",
+            "noise:3",
+            "laplace:0.5",
+            "laplace:0.5,100",
+        ] {
+            assert_eq!(
+                strategy.parse::<NumericMask>().unwrap().to_string(),
+                strategy
+            );
+        }
+        assert!("bucket:0".parse::<NumericMask>().is_err());
+        assert!("round:-5".parse::<NumericMask>().is_err());
+        assert!("truncate:1.5".parse::<NumericMask>().is_err());
+        assert!("laplace:1,x".parse::<NumericMask>().is_err());
+        assert!("median:1".parse::<NumericMask>().is_err());
+    }
+}
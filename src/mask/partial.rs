@@ -0,0 +1,186 @@
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+const DEFAULT_LAST: usize = 4;
+const DEFAULT_MASK_CHAR: char = '*';
+
+/// Options of the partial strategy: how many characters stay visible at the start and at
+/// the end, and which character hides the rest.
+///
+/// Written after `partial:` as comma separated `first=N`, `last=N`, `char=C` and `email`,
+/// e.g. `partial:first=2,last=2,char=#`, so the mask char can't be a comma. With `email`
+/// only the name before `@` is masked, showing its first character by default:
+/// `j***@example.com`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialMask {
+    first: usize,
+    last: usize,
+    mask_char: char,
+    email: bool,
+}
+
+impl Default for PartialMask {
+    fn default() -> Self {
+        PartialMask {
+            first: 0,
+            last: DEFAULT_LAST,
+            mask_char: DEFAULT_MASK_CHAR,
+            email: false,
+        }
+    }
+}
+
+impl PartialMask {
+    pub fn apply(&self, value: &Value) -> Value {
+        let text = match value {
+            Value::String(text) => text.to_owned(),
+            Value::Number(number) => number.to_string(),
+            _ => return super::placeholder(value),
+        };
+        if self.email {
+            if let Some((name, domain)) = text.rsplit_once('@') {
+                return Value::String(format!("{}@{}", self.mask(name), domain));
+            }
+        }
+        Value::String(self.mask(&text))
+    }
+
+    // masks everything if visible parts would reveal the whole text
+    fn mask(&self, text: &str) -> String {
+        let len = text.chars().count();
+        let reveal = self.first + self.last < len;
+        text.chars()
+            .enumerate()
+            .map(
+                |(i, c)| match reveal && (i < self.first || i >= len - self.last) {
+                    true => c,
+                    false => self.mask_char,
+                },
+            )
+            .collect()
+    }
+}
+
+impl FromStr for PartialMask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut first = None;
+        let mut last = None;
+        let mut mask_char = DEFAULT_MASK_CHAR;
+        let mut email = false;
+        let count = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("invalid number of characters '{}'", value))
+        };
+        for option in s.split(',').filter(|option| !option.is_empty()) {
+            match option.split_once('=') {
+                Some(("first", value)) => first = Some(count(value)?),
+                Some(("last", value)) => last = Some(count(value)?),
+                Some(("char", value)) => {
+                    let mut chars = value.chars();
+                    mask_char = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => {
+                            return Err(format!(
+                                "mask char must be a single character other than a comma, got '{}'",
+                                value
+                            ))
+                        }
+                    };
+                }
+                None if option == "email" => email = true,
+                _ => {
+                    return Err(format!(
+                    "unknown partial option '{}', expected one of: first=N, last=N, char=C, email",
+                    option
+                ))
+                }
+            }
+        }
+        // emails show the first character of the name, other values the last four characters
+        let (default_first, default_last) = match email {
+            true => (1, 0),
+            false => (0, DEFAULT_LAST),
+        };
+        Ok(PartialMask {
+            first: first.unwrap_or(default_first),
+            last: last.unwrap_or(default_last),
+            mask_char,
+            email,
+        })
+    }
+}
+
+impl fmt::Display for PartialMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "first={},last={},char={}",
+            self.first, self.last, self.mask_char
+        )?;
+        if self.email {
+            write!(f, ",email")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn mask(options: &str, value: Value) -> Value {
+        options.parse::<PartialMask>().unwrap().apply(&value)
+    }
+
+    #[test]
+    fn test_default_shows_last_four() {
+        assert_eq!(
+            mask("", json!("4111111111111111")),
+            json!("************1111")
+        );
+        assert_eq!(mask("", json!("abc")), json!("***"));
+        assert_eq!(mask("", json!(123456)), json!("**3456"));
+    }
+
+    #[test]
+    fn test_reveal_windows_and_char() {
+        assert_eq!(
+            mask("first=2,last=2,char=#", json!("secret-value")),
+            json!("se########ue")
+        );
+        assert_eq!(
+            mask("first=6,last=0", json!("411111xxxxxx")),
+            json!("411111******")
+        );
+        assert_eq!(mask("first=3,last=3", json!("abcdef")), json!("******"));
+    }
+
+    #[test]
+    fn test_email() {
+        assert_eq!(
+            mask("email", json!("john@example.com")),
+            json!("j***@example.com")
+        );
+        assert_eq!(
+            mask("email,last=1", json!("john@example.com")),
+            json!("j**n@example.com")
+        );
+        // not an email, masked as a plain value
+        assert_eq!(mask("email", json!("john")), json!("j***"));
+    }
+
+    #[test]
+    fn test_parse_errors_and_display() {
+        assert!("first=x".parse::<PartialMask>().is_err());
+        assert!("char=ab".parse::<PartialMask>().is_err());
+        assert!("char=,".parse::<PartialMask>().is_err());
+        assert!("middle=1".parse::<PartialMask>().is_err());
+        let options: PartialMask = "email,char=#".parse().unwrap();
+        assert_eq!(options.to_string().parse(), Ok(options));
+    }
+}
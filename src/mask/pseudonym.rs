@@ -0,0 +1,222 @@
+use crate::in_place;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const DEFAULT_PREFIX: &str = "user";
+
+/// Which values share numbering, written as `run` or `key`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum PseudonymScope {
+    /// the same value gets the same pseudonym under any key
+    #[default]
+    Run,
+    /// every key has its own numbering, named after the key
+    Key,
+}
+
+/// Replaces values with pseudonyms like `user_1`, `user_2`, the same value always gets
+/// the same pseudonym during the run, so masked records can still be linked together.
+pub struct Pseudonymizer {
+    scope: PseudonymScope,
+    prefix: Option<String>,
+    map_file: Option<PathBuf>,
+    // scope (empty for the whole run) -> serialized original value -> pseudonym
+    pseudonyms: HashMap<String, HashMap<String, String>>,
+    // scope -> pseudonym -> original value, in order of appearance
+    originals: Map<String, Value>,
+}
+
+impl Pseudonymizer {
+    /// `prefix` replaces the default one: `user` for the whole run and the key name per key,
+    /// where it goes in front of the key name.
+    pub fn new(scope: PseudonymScope, prefix: Option<String>) -> Self {
+        Pseudonymizer {
+            scope,
+            prefix,
+            map_file: None,
+            pseudonyms: HashMap::new(),
+            originals: Map::new(),
+        }
+    }
+
+    /// Keeps pseudonyms with their original values in the file when saved.
+    pub fn with_map_file(mut self, map_file: Option<PathBuf>) -> Self {
+        self.map_file = map_file;
+        self
+    }
+
+    pub fn pseudonymize(&mut self, key: &str, value: &Value) -> Value {
+        let scope = match self.scope {
+            PseudonymScope::Run => "",
+            PseudonymScope::Key => key,
+        };
+        let pseudonyms = self.pseudonyms.entry(scope.to_owned()).or_default();
+        let original = value.to_string();
+        if let Some(pseudonym) = pseudonyms.get(&original) {
+            return Value::String(pseudonym.to_owned());
+        }
+        // keys keep their name after the prefix, so numbers of different keys don't clash
+        let prefix = match (&self.prefix, self.scope) {
+            (Some(prefix), PseudonymScope::Key) if !key.is_empty() => {
+                format!("{}_{}", prefix, key)
+            }
+            (Some(prefix), _) => prefix.to_owned(),
+            (None, PseudonymScope::Key) if !key.is_empty() => key.to_owned(),
+            (None, _) => DEFAULT_PREFIX.to_owned(),
+        };
+        let pseudonym = format!("{}_{}", prefix, pseudonyms.len() + 1);
+        pseudonyms.insert(original, pseudonym.clone());
+
+        let originals = match self.scope {
+            PseudonymScope::Run => &mut self.originals,
+            PseudonymScope::Key => self
+                .originals
+                .entry(scope)
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .expect("scopes are objects"),
+        };
+        originals.insert(pseudonym.clone(), value.clone());
+        Value::String(pseudonym)
+    }
+
+    /// Writes the mapping to the map file if it's set: pseudonym -> original value,
+    /// grouped by key when every key has its own numbering.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.map_file else {
+            return Ok(());
+        };
+        let text = serde_json::to_string_pretty(&self.originals)?;
+        // the map holds the original values, so it's as private as the vault
+        in_place::write_atomically(path, (text + "\n").as_bytes())
+            .with_context(|| format!("could not write pseudonym map: {}", path.display()))
+    }
+}
+
+impl FromStr for PseudonymScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "run" => Ok(PseudonymScope::Run),
+            "key" => Ok(PseudonymScope::Key),
+            _ => Err(format!(
+                "unknown pseudonym scope '{}', expected one of: run, key",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PseudonymScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PseudonymScope::Run => "run",
+            PseudonymScope::Key => "key",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TryFrom<String> for PseudonymScope {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PseudonymScope> for String {
+    fn from(value: PseudonymScope) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_same_value_same_pseudonym() {
+        let mut pseudonymizer = Pseudonymizer::new(PseudonymScope::Run, None);
+        assert_eq!(
+            pseudonymizer.pseudonymize("email", &json!("a@b.c")),
+            "user_1"
+        );
+        assert_eq!(
+            pseudonymizer.pseudonymize("owner", &json!("x@y.z")),
+            "user_2"
+        );
+        assert_eq!(
+            pseudonymizer.pseudonymize("owner", &json!("a@b.c")),
+            "user_1"
+        );
+        // numbers and strings with the same text are different values
+        assert_eq!(pseudonymizer.pseudonymize("id", &json!(1)), "user_3");
+        assert_eq!(pseudonymizer.pseudonymize("id", &json!("1")), "user_4");
+        assert_eq!(
+            Value::Object(pseudonymizer.originals),
+            json!({"user_1": "a@b.c", "user_2": "x@y.z", "user_3": 1, "user_4": "1"})
+        );
+    }
+
+    #[test]
+    fn test_scope_per_key() {
+        let mut pseudonymizer = Pseudonymizer::new(PseudonymScope::Key, None);
+        assert_eq!(
+            pseudonymizer.pseudonymize("email", &json!("a@b.c")),
+            "email_1"
+        );
+        assert_eq!(
+            pseudonymizer.pseudonymize("owner", &json!("a@b.c")),
+            "owner_1"
+        );
+        assert_eq!(
+            pseudonymizer.pseudonymize("email", &json!("a@b.c")),
+            "email_1"
+        );
+        assert_eq!(
+            Value::Object(pseudonymizer.originals),
+            json!({"email": {"email_1": "a@b.c"}, "owner": {"owner_1": "a@b.c"}})
+        );
+    }
+
+    #[test]
+    fn test_prefix_per_key() {
+        let mut pseudonymizer = Pseudonymizer::new(PseudonymScope::Key, Some(String::from("p")));
+        assert_eq!(
+            pseudonymizer.pseudonymize("email", &json!("a@b.c")),
+            "p_email_1"
+        );
+        assert_eq!(
+            pseudonymizer.pseudonymize("owner", &json!("x@y.z")),
+            "p_owner_1"
+        );
+    }
+
+    #[test]
+    fn test_map_file_is_private() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("pseudonyms.json");
+        let mut pseudonymizer =
+            Pseudonymizer::new(PseudonymScope::Run, None).with_map_file(Some(path.clone()));
+        pseudonymizer.pseudonymize("email", &json!("a@b.c"));
+        pseudonymizer.save().unwrap();
+
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved, json!({"user_1": "a@b.c"}));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
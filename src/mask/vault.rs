@@ -0,0 +1,237 @@
+use super::kdf;
+use crate::in_place;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use rand::Rng;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// environment variable with the secret used to encrypt the vault
+pub const SECRET_ENV: &str = "HIDE_VAULT_KEY";
+
+const TOKEN_PREFIX: &str = "tok_";
+const TOKEN_HEX_LEN: usize = 16;
+const MAGIC: &[u8] = b"HIDEVLT2";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+static TOKEN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!("{}[0-9a-f]{{{}}}", TOKEN_PREFIX, TOKEN_HEX_LEN)).unwrap()
+});
+
+/// Encrypted file with token -> original value mapping, used to restore tokenized values.
+pub struct Vault {
+    path: PathBuf,
+    cipher: Aes256Gcm,
+    // salt of the key, kept in the header of the file
+    salt: [u8; SALT_LEN],
+    tokens: BTreeMap<String, Value>,
+    // serialized original value -> token, so the same value always gets the same token
+    values: HashMap<String, String>,
+}
+
+impl Vault {
+    /// Opens existing vault or creates an empty one if the file doesn't exist yet.
+    pub fn open(path: &Path, secret: &[u8]) -> Result<Self> {
+        let (salt, cipher, tokens) = match path.exists() {
+            true => decrypt(path, secret)?,
+            false => {
+                let salt: [u8; SALT_LEN] = OsRng.gen();
+                (salt, cipher(secret, &salt)?, BTreeMap::new())
+            }
+        };
+        let values = tokens
+            .iter()
+            .map(|(token, value)| (value.to_string(), token.to_owned()))
+            .collect();
+        Ok(Vault {
+            path: path.to_owned(),
+            cipher,
+            salt,
+            tokens,
+            values,
+        })
+    }
+
+    /// Reads the secret from the key file if given, otherwise from `HIDE_VAULT_KEY`.
+    pub fn load_secret(key_file: Option<&Path>) -> Result<Vec<u8>> {
+        if let Some(path) = key_file {
+            let secret = fs::read(path)
+                .with_context(|| format!("could not read vault key file: {}", path.display()))?;
+            return Ok(secret.trim_ascii_end().to_vec());
+        }
+        match std::env::var(SECRET_ENV) {
+            Ok(secret) if !secret.is_empty() => Ok(secret.into_bytes()),
+            _ => bail!(
+                "vault requires a secret, set {} or use --vault-key-file",
+                SECRET_ENV
+            ),
+        }
+    }
+
+    /// Returns token for the value, creating a new one if the value wasn't seen before.
+    pub fn tokenize(&mut self, value: &Value) -> String {
+        let serialized = value.to_string();
+        if let Some(token) = self.values.get(&serialized) {
+            return token.to_owned();
+        }
+        let mut rng = rand::thread_rng();
+        let token = loop {
+            let token = format!("{}{}", TOKEN_PREFIX, hex::encode(rng.gen::<[u8; 8]>()));
+            if !self.tokens.contains_key(&token) {
+                break token;
+            }
+        };
+        self.tokens.insert(token.clone(), value.clone());
+        self.values.insert(serialized, token.clone());
+        token
+    }
+
+    /// Replaces tokens in the text with original values. If the whole text is a token,
+    /// the original value is returned with its type.
+    pub fn restore(&self, text: &str) -> Option<Value> {
+        if let Some(value) = self.tokens.get(text) {
+            return Some(value.clone());
+        }
+        let mut restored = false;
+        let text = TOKEN_REGEX.replace_all(text, |captures: &regex::Captures| {
+            match self.tokens.get(&captures[0]) {
+                Some(value) => {
+                    restored = true;
+                    match value {
+                        Value::String(text) => text.to_owned(),
+                        other => other.to_string(),
+                    }
+                }
+                None => captures[0].to_owned(),
+            }
+        });
+        restored.then(|| Value::String(text.into_owned()))
+    }
+
+    /// Writes the vault atomically, readable only by the owner.
+    pub fn save(&self) -> Result<()> {
+        let plain = serde_json::to_vec(&self.tokens)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let encrypted = self
+            .cipher
+            .encrypt(&nonce, plain.as_slice())
+            .map_err(|_| anyhow!("could not encrypt vault"))?;
+        let mut content = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + encrypted.len());
+        content.extend_from_slice(MAGIC);
+        content.extend_from_slice(&self.salt);
+        content.extend_from_slice(&nonce);
+        content.extend_from_slice(&encrypted);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("could not create vault directory: {}", dir.display()))?;
+        }
+        in_place::write_atomically(&self.path, &content)
+            .with_context(|| format!("could not write vault: {}", self.path.display()))
+    }
+}
+
+fn cipher(secret: &[u8], salt: &[u8]) -> Result<Aes256Gcm> {
+    let key = kdf::derive_key(secret, salt)?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+type Decrypted = ([u8; SALT_LEN], Aes256Gcm, BTreeMap<String, Value>);
+
+fn decrypt(path: &Path, secret: &[u8]) -> Result<Decrypted> {
+    let content =
+        fs::read(path).with_context(|| format!("could not read vault: {}", path.display()))?;
+    if content.len() < MAGIC.len() + SALT_LEN + NONCE_LEN || !content.starts_with(MAGIC) {
+        bail!("not a vault file: {}", path.display());
+    }
+    let (salt, rest) = content[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, encrypted) = rest.split_at(NONCE_LEN);
+    let cipher = cipher(secret, salt)?;
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), encrypted)
+        .map_err(|_| anyhow!("could not decrypt vault, wrong key?"))?;
+    let tokens = serde_json::from_slice(&plain).with_context(|| "vault is corrupted")?;
+    Ok((salt.try_into()?, cipher, tokens))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_same_value_same_token() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let mut vault = Vault::open(&dir.path().join("vault"), b"secret").unwrap();
+
+        let token = vault.tokenize(&json!("alice"));
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + TOKEN_HEX_LEN);
+        assert_eq!(vault.tokenize(&json!("alice")), token);
+        assert_ne!(vault.tokenize(&json!("bob")), token);
+    }
+
+    #[test]
+    fn test_restore_keeps_type() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let mut vault = Vault::open(&dir.path().join("vault"), b"secret").unwrap();
+
+        let token = vault.tokenize(&json!(42));
+        assert_eq!(vault.restore(&token), Some(json!(42)));
+        assert_eq!(vault.restore("tok_0000000000000000"), None);
+        assert_eq!(vault.restore("no tokens here"), None);
+    }
+
+    #[test]
+    fn test_restore_tokens_inside_text() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let mut vault = Vault::open(&dir.path().join("vault"), b"secret").unwrap();
+
+        let token = vault.tokenize(&json!("alice"));
+        let text = format!("Hello, {}!", token);
+        assert_eq!(vault.restore(&text), Some(json!("Hello, alice!")));
+    }
+
+    #[test]
+    fn test_save_and_open() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("vault");
+        let mut vault = Vault::open(&path, b"secret").unwrap();
+        let token = vault.tokenize(&json!("alice"));
+        vault.save().unwrap();
+
+        let content = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("alice"));
+
+        let vault = Vault::open(&path, b"secret").unwrap();
+        assert_eq!(vault.restore(&token), Some(json!("alice")));
+        assert!(Vault::open(&path, b"wrong").is_err());
+    }
+
+    #[test]
+    fn test_salted_and_private() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let header = |name: &str| {
+            let path = dir.path().join(name);
+            Vault::open(&path, b"secret").unwrap().save().unwrap();
+            let content = fs::read(&path).unwrap();
+            content[..MAGIC.len() + SALT_LEN].to_vec()
+        };
+        // the same secret gives different keys in different vaults
+        assert_ne!(header("first"), header("second"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("first"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use anyhow::{anyhow, Error, Result};
+use serde_json::{Map, Value};
+use std::io::BufRead;
+
+// same nesting limit as serde_json, so both paths accept the same documents
+const RECURSION_LIMIT: usize = 128;
+
+/// Reads JSON tokens one by one, never keeping more than a single scalar in memory.
+pub struct Lexer<R> {
+    reader: R,
+    line: usize,
+    column: usize,
+    // `//` and `/* */` comments are skipped like whitespace
+    comments: bool,
+    // raw bytes consumed since `capture` was called
+    captured: Option<Vec<u8>>,
+}
+
+impl<R: BufRead> Lexer<R> {
+    pub fn new(reader: R) -> Self {
+        Lexer {
+            reader,
+            line: 1,
+            column: 0,
+            comments: false,
+            captured: None,
+        }
+    }
+
+    /// Accepts comments, as in JSONC files.
+    pub fn with_comments(mut self) -> Self {
+        self.comments = true;
+        self
+    }
+
+    /// Starts keeping raw bytes of everything read, until `take_captured` is called.
+    pub fn capture(&mut self) {
+        self.captured = Some(Vec::new());
+    }
+
+    pub fn take_captured(&mut self) -> Vec<u8> {
+        self.captured.take().unwrap_or_default()
+    }
+
+    /// Reads whitespace and comments, returning them as is.
+    pub fn trivia(&mut self) -> Result<Vec<u8>> {
+        self.capture();
+        self.skip_trivia()?;
+        Ok(self.take_captured())
+    }
+
+    /// Returns the next meaningful byte without consuming it, whitespace is skipped.
+    pub fn peek(&mut self) -> Result<Option<u8>> {
+        self.skip_trivia()?;
+        self.peek_byte()
+    }
+
+    fn skip_trivia(&mut self) -> Result<()> {
+        while let Some(byte) = self.peek_byte()? {
+            match byte {
+                b' ' | b'\t' | b'\n' | b'\r' => {
+                    self.bump()?;
+                }
+                b'/' if self.comments => self.comment()?,
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn comment(&mut self) -> Result<()> {
+        self.bump()?;
+        match self.bump()? {
+            Some(b'/') => while !matches!(self.bump()?, Some(b'\n') | None) {},
+            Some(b'*') => {
+                let mut previous = 0;
+                loop {
+                    match self.bump()? {
+                        Some(b'/') if previous == b'*' => break,
+                        Some(byte) => previous = byte,
+                        None => return Err(self.error("EOF while parsing a comment")),
+                    }
+                }
+            }
+            _ => return Err(self.error("expected comment")),
+        }
+        Ok(())
+    }
+
+    /// Consumes the next meaningful byte, failing if it's not the expected one.
+    pub fn expect(&mut self, expected: u8) -> Result<()> {
+        match self.peek()? {
+            Some(byte) if byte == expected => {
+                self.bump()?;
+                Ok(())
+            }
+            Some(byte) => Err(self.error(format!(
+                "expected '{}', found '{}'",
+                expected as char, byte as char
+            ))),
+            None => Err(self.error(format!(
+                "expected '{}', found end of file",
+                expected as char
+            ))),
+        }
+    }
+
+    /// Consumes `,` and returns true, or consumes the closing byte and returns false.
+    pub fn next_item(&mut self, close: u8) -> Result<bool> {
+        match self.peek()? {
+            Some(b',') => {
+                self.bump()?;
+                Ok(true)
+            }
+            Some(byte) if byte == close => {
+                self.bump()?;
+                Ok(false)
+            }
+            _ => Err(self.error(format!("expected ',' or '{}'", close as char))),
+        }
+    }
+
+    /// Consumes the byte returned by `peek`.
+    pub fn consume(&mut self) -> Result<()> {
+        self.bump()?;
+        Ok(())
+    }
+
+    /// Consumes the closing byte if the container is empty.
+    pub fn empty(&mut self, close: u8) -> Result<bool> {
+        if self.peek()? == Some(close) {
+            self.bump()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn expect_end(&mut self) -> Result<()> {
+        match self.peek()? {
+            None => Ok(()),
+            Some(_) => Err(self.error("trailing characters")),
+        }
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut raw = vec![b'"'];
+        loop {
+            let byte = self
+                .bump()?
+                .ok_or_else(|| self.error("EOF while parsing a string"))?;
+            raw.push(byte);
+            match byte {
+                b'"' => break,
+                b'\\' => raw.extend(self.bump()?),
+                0..=0x1f => return Err(self.error("control character in string")),
+                _ => {}
+            }
+        }
+        // escapes and UTF-8 are decoded exactly like in the tree-based path
+        serde_json::from_slice(&raw).map_err(|e| self.error(e))
+    }
+
+    /// Reads a string, number, bool or null.
+    pub fn scalar(&mut self) -> Result<Value> {
+        match self.peek()? {
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let mut raw = Vec::new();
+                while let Some(byte @ (b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')) =
+                    self.peek_byte()?
+                {
+                    raw.push(byte);
+                    self.bump()?;
+                }
+                serde_json::from_slice(&raw).map_err(|e| self.error(e))
+            }
+            Some(byte) => Err(self.error(format!("expected value, found '{}'", byte as char))),
+            None => Err(self.error("EOF while parsing a value")),
+        }
+    }
+
+    /// Reads the whole value into memory, used for values that are hidden as a whole.
+    pub fn value(&mut self, depth: usize) -> Result<Value> {
+        match self.peek()? {
+            Some(b'{') => {
+                self.enter(depth)?;
+                self.bump()?;
+                let mut map = Map::new();
+                if !self.empty(b'}')? {
+                    loop {
+                        let key = self.string()?;
+                        if map.contains_key(&key) {
+                            return Err(self.duplicate_key(&key));
+                        }
+                        self.expect(b':')?;
+                        map.insert(key, self.value(depth + 1)?);
+                        if !self.next_item(b'}')? {
+                            break;
+                        }
+                    }
+                }
+                Ok(Value::Object(map))
+            }
+            Some(b'[') => {
+                self.enter(depth)?;
+                self.bump()?;
+                let mut array = Vec::new();
+                if !self.empty(b']')? {
+                    loop {
+                        array.push(self.value(depth + 1)?);
+                        if !self.next_item(b']')? {
+                            break;
+                        }
+                    }
+                }
+                Ok(Value::Array(array))
+            }
+            _ => self.scalar(),
+        }
+    }
+
+    /// Fails if one more nested container would exceed the recursion limit.
+    pub fn enter(&self, depth: usize) -> Result<()> {
+        match depth + 1 < RECURSION_LIMIT {
+            true => Ok(()),
+            false => Err(self.error("recursion limit exceeded")),
+        }
+    }
+
+    /// Error for a key repeated in one object, the document can't keep both values.
+    pub fn duplicate_key(&self, key: &str) -> Error {
+        self.error(format!("duplicate key '{}'", key))
+    }
+
+    pub fn error(&self, message: impl std::fmt::Display) -> Error {
+        anyhow!(
+            "{} at line {} column {}",
+            message,
+            self.line,
+            self.column + 1
+        )
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value> {
+        for expected in literal.bytes() {
+            if self.bump()? != Some(expected) {
+                return Err(self.error(format!("expected '{}'", literal)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self) -> Result<Option<u8>> {
+        let byte = self.peek_byte()?;
+        if let Some(byte) = byte {
+            self.reader.consume(1);
+            if let Some(captured) = self.captured.as_mut() {
+                captured.push(byte);
+            }
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+        Ok(byte)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn lexer(text: &str) -> Lexer<&[u8]> {
+        Lexer::new(text.as_bytes())
+    }
+
+    #[test]
+    fn test_scalars() {
+        let mut lexer = lexer(r#" "a\"bé" -1.5e3 true null "#);
+        assert_eq!(lexer.scalar().unwrap(), json!("a\"bé"));
+        assert_eq!(lexer.scalar().unwrap(), json!(-1500.0));
+        assert_eq!(lexer.scalar().unwrap(), json!(true));
+        assert_eq!(lexer.scalar().unwrap(), json!(null));
+        assert!(lexer.expect_end().is_ok());
+    }
+
+    #[test]
+    fn test_value() {
+        let text = r#"{"b": [1, {"c": null}], "a": {}}"#;
+        let value = lexer(text).value(0).unwrap();
+        assert_eq!(value, serde_json::from_str::<Value>(text).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(lexer("tru").scalar().is_err());
+        assert!(lexer("01").scalar().is_err());
+        assert!(lexer("\"unclosed").scalar().is_err());
+        assert!(lexer("[1 2]").value(0).is_err());
+        let error = lexer("{\n  \"a\": x}").value(0).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_comments_and_trivia() {
+        let text = "// head\n {\"a\" /* inline */ : 1}";
+        let mut jsonc = lexer(text).with_comments();
+        assert_eq!(jsonc.trivia().unwrap(), b"// head\n ");
+        assert_eq!(
+            jsonc.value(0).unwrap(),
+            serde_json::from_str::<Value>("{\"a\": 1}").unwrap()
+        );
+        assert!(lexer("// comment\n1").scalar().is_err());
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut lexer = lexer("1.50e1 ");
+        lexer.capture();
+        assert_eq!(lexer.scalar().unwrap(), json!(15.0));
+        assert_eq!(lexer.take_captured(), b"1.50e1");
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let deep = format!("{}{}", "[".repeat(200), "]".repeat(200));
+        assert!(lexer(&deep).value(0).is_err());
+        assert!(serde_json::from_str::<Value>(&deep).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::keys_storage::{JsonPath, KeysStorage, PathSegment};
+use crate::mask::Transform;
+use anyhow::Result;
+use lexer::Lexer;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use writer::PrettyWriter;
+
+mod lexer;
+mod preserve;
+mod writer;
+
+pub use preserve::preserve;
+
+/// Masks a JSON document while reading it, so memory use doesn't depend on the document size.
+/// Only values under sensitive keys are read into memory before they are hidden.
+///
+/// The output is the same as `serde_json::to_string_pretty` of the tree-based result.
+pub fn mask(
+    reader: impl BufRead,
+    writer: impl Write,
+    storage: &impl KeysStorage,
+    masker: &mut impl Transform,
+) -> Result<()> {
+    let mut engine = Engine {
+        lexer: Lexer::new(reader),
+        writer: PrettyWriter::new(writer),
+        storage,
+        masker,
+        path: JsonPath::new(),
+    };
+    engine.document()?;
+    engine.writer.flush()
+}
+
+struct Engine<'a, R, W, S, T> {
+    lexer: Lexer<R>,
+    writer: PrettyWriter<W>,
+    storage: &'a S,
+    masker: &'a mut T,
+    path: JsonPath,
+}
+
+impl<R: BufRead, W: Write, S: KeysStorage, T: Transform> Engine<'_, R, W, S, T> {
+    fn document(&mut self) -> Result<()> {
+        let value = if self.storage.contains_path(&self.path) {
+            let value = self.lexer.value(0)?;
+            self.masker.hide(&value, &self.path)
+        } else {
+            match self.lexer.peek()? {
+                Some(b'{') => return self.object(0).and_then(|_| self.lexer.expect_end()),
+                Some(b'[') => return self.array(0).and_then(|_| self.lexer.expect_end()),
+                _ => {
+                    let value = self.lexer.scalar()?;
+                    self.masker.inspect(&value, &self.path)
+                }
+            }
+        };
+        self.lexer.expect_end()?;
+        self.writer.value(&value.unwrap_or(Value::Null))
+    }
+
+    fn object(&mut self, depth: usize) -> Result<()> {
+        self.lexer.enter(depth)?;
+        self.lexer.expect(b'{')?;
+        self.writer.begin(b'{')?;
+        // keys of this object, a repeated one fails like in the tree-based path
+        let mut keys = HashSet::new();
+        if !self.lexer.empty(b'}')? {
+            loop {
+                let key = self.lexer.string()?;
+                if !keys.insert(key.clone()) {
+                    return Err(self.lexer.duplicate_key(&key));
+                }
+                self.lexer.expect(b':')?;
+                self.path.push(PathSegment::Key(key.clone()));
+                if self.storage.contains(&key) || self.storage.contains_path(&self.path) {
+                    let value = self.lexer.value(depth + 1)?;
+                    if let Some(value) = self.masker.hide(&value, &self.path) {
+                        self.writer.key(&key)?;
+                        self.writer.value(&value)?;
+                    }
+                } else {
+                    match self.lexer.peek()? {
+                        Some(b'{') => {
+                            self.writer.key(&key)?;
+                            self.object(depth + 1)?;
+                        }
+                        Some(b'[') => {
+                            self.writer.key(&key)?;
+                            self.array(depth + 1)?;
+                        }
+                        _ => {
+                            let value = self.lexer.scalar()?;
+                            if let Some(value) = self.masker.inspect(&value, &self.path) {
+                                self.writer.key(&key)?;
+                                self.writer.value(&value)?;
+                            }
+                        }
+                    }
+                }
+                self.path.pop();
+                if !self.lexer.next_item(b'}')? {
+                    break;
+                }
+            }
+        }
+        self.writer.end(b'}')
+    }
+
+    fn array(&mut self, depth: usize) -> Result<()> {
+        self.lexer.enter(depth)?;
+        self.lexer.expect(b'[')?;
+        self.writer.begin(b'[')?;
+        if !self.lexer.empty(b']')? {
+            let mut index = 0;
+            loop {
+                self.path.push(PathSegment::Index(index));
+                if self.storage.contains_path(&self.path) {
+                    let item = self.lexer.value(depth + 1)?;
+                    if let Some(item) = self.masker.hide(&item, &self.path) {
+                        self.writer.item()?;
+                        self.writer.value(&item)?;
+                    }
+                } else {
+                    match self.lexer.peek()? {
+                        Some(b'{') => {
+                            self.writer.item()?;
+                            self.object(depth + 1)?;
+                        }
+                        Some(b'[') => {
+                            self.writer.item()?;
+                            self.array(depth + 1)?;
+                        }
+                        _ => {
+                            let item = self.lexer.scalar()?;
+                            if let Some(item) = self.masker.inspect(&item, &self.path) {
+                                self.writer.item()?;
+                                self.writer.value(&item)?;
+                            }
+                        }
+                    }
+                }
+                self.path.pop();
+                index += 1;
+                if !self.lexer.next_item(b']')? {
+                    break;
+                }
+            }
+        }
+        self.writer.end(b']')
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys_storage::InMemoryKeysStorage;
+    use crate::mask::{MaskStrategy, Masker};
+    use std::collections::{HashMap, HashSet};
+
+    const INPUT: &str = r#"{
+        "users": [
+            {"name": "alice", "password": "secret", "age": 30, "tags": []},
+            {"name": "bob", "password": {"old": "a", "new": ["b", 1]}, "ssn": "123-45-6789"}
+        ],
+        "zeta": 1.50,
+        "token": "abc",
+        "empty": {},
+        "nested": [[1e3, null], [true]]
+    }"#;
+
+    fn storage(keys: &[&str]) -> InMemoryKeysStorage {
+        let keys: HashSet<String> = keys.iter().map(|key| key.to_string()).collect();
+        InMemoryKeysStorage::init_with(&keys).unwrap()
+    }
+
+    fn masker() -> Masker {
+        let key_strategies = HashMap::from([(String::from("token"), MaskStrategy::Remove)]);
+        Masker::new(MaskStrategy::Placeholder, key_strategies).unwrap()
+    }
+
+    // compares streaming output with the tree-based one byte by byte
+    fn assert_same_as_tree(input: &str, keys: &[&str]) {
+        let storage = storage(keys);
+        let mut output = Vec::new();
+        mask(input.as_bytes(), &mut output, &storage, &mut masker()).unwrap();
+
+        let tree = crate::hide_by_keys(
+            &storage,
+            &mut masker(),
+            &serde_json::from_str(input).unwrap(),
+        );
+        let expected = serde_json::to_string_pretty(&tree).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_same_as_tree_for_keys() {
+        assert_same_as_tree(INPUT, &["password", "token", "zeta"]);
+    }
+
+    #[test]
+    fn test_same_as_tree_for_paths() {
+        assert_same_as_tree(INPUT, &["$.users[1]", "$..age", "$.nested[0][0]"]);
+        assert_same_as_tree(INPUT, &["$"]);
+    }
+
+    #[test]
+    fn test_same_as_tree_for_scalars_and_empty() {
+        assert_same_as_tree(" \"text\" ", &[]);
+        assert_same_as_tree("[]", &[]);
+        assert_same_as_tree("[{\"token\": 1}]", &["token"]);
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let storage = storage(&[]);
+        for input in ["{\"a\": 1", "{\"a\": 1} 2", "[1,]", ""] {
+            let result = mask(input.as_bytes(), Vec::new(), &storage, &mut masker());
+            assert!(result.is_err(), "{} should fail", input);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        for (input, keys) in [
+            (r#"{"a": 1, "a": 2}"#, &[][..]),
+            (r#"{"password": {"a": 1, "a": 2}}"#, &["password"][..]),
+        ] {
+            let result = mask(input.as_bytes(), Vec::new(), &storage(keys), &mut masker());
+            let error = result.unwrap_err().to_string();
+            assert!(error.contains("duplicate key 'a'"), "{}", error);
+        }
+        let input = r#"[{"a": 1}, {"a": 2}]"#;
+        assert_same_as_tree(input, &[]);
+    }
+}
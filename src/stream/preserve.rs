@@ -0,0 +1,290 @@
+use super::lexer::Lexer;
+use crate::keys_storage::{JsonPath, KeysStorage, PathSegment};
+use crate::mask::Transform;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+
+/// Masks a JSON document keeping everything else byte for byte: whitespace, comments,
+/// key order and number literals. Only masked values are written anew, compactly.
+pub fn preserve(
+    reader: impl BufRead,
+    writer: impl Write,
+    storage: &impl KeysStorage,
+    masker: &mut impl Transform,
+) -> Result<()> {
+    let mut preserver = Preserver {
+        lexer: Lexer::new(reader).with_comments(),
+        writer,
+        storage,
+        masker,
+        path: JsonPath::new(),
+    };
+    preserver.document()?;
+    preserver.writer.flush()?;
+    Ok(())
+}
+
+struct Preserver<'a, R, W, S, T> {
+    lexer: Lexer<R>,
+    writer: W,
+    storage: &'a S,
+    masker: &'a mut T,
+    path: JsonPath,
+}
+
+// bytes that are not written yet: separators and comments between entries. They are held
+// back until the next entry is known to stay, so a removed entry takes its comma with it.
+struct Pending {
+    bytes: Vec<u8>,
+    kept_any: bool,
+}
+
+impl<R: BufRead, W: Write, S: KeysStorage, T: Transform> Preserver<'_, R, W, S, T> {
+    fn document(&mut self) -> Result<()> {
+        let leading = self.lexer.trivia()?;
+        self.writer.write_all(&leading)?;
+        if self.storage.contains_path(&self.path) {
+            let path = self.path.clone();
+            let value = self.masked(|masker, value| masker.hide(value, &path), true, 0)?;
+            self.writer.write_all(&value.unwrap_or(b"null".to_vec()))?;
+        } else {
+            match self.lexer.peek()? {
+                Some(b'{') => self.container(b'}', 0)?,
+                Some(b'[') => self.container(b']', 0)?,
+                _ => {
+                    let path = self.path.clone();
+                    let value =
+                        self.masked(|masker, value| masker.inspect(value, &path), false, 0)?;
+                    self.writer.write_all(&value.unwrap_or(b"null".to_vec()))?;
+                }
+            }
+        }
+        let trailing = self.lexer.trivia()?;
+        self.writer.write_all(&trailing)?;
+        self.lexer.expect_end()
+    }
+
+    // object or array, entries are `"key": value` for objects and values for arrays
+    fn container(&mut self, close: u8, depth: usize) -> Result<()> {
+        self.lexer.enter(depth)?;
+        self.lexer.capture();
+        self.lexer.consume()?;
+        let open = self.lexer.take_captured();
+        self.writer.write_all(&open)?;
+        let mut pending = Pending {
+            bytes: self.lexer.trivia()?,
+            kept_any: false,
+        };
+        let mut index = 0;
+        let mut keys = HashSet::new();
+        while self.lexer.peek()? != Some(close) {
+            let kept = match close {
+                b'}' => self.entry(&mut pending, &mut keys, depth)?,
+                _ => self.item(&mut pending, index, depth)?,
+            };
+            index += 1;
+            let after = self.lexer.trivia()?;
+            match self.lexer.peek()? {
+                Some(b',') => {
+                    self.lexer.consume()?;
+                    let before_next = self.lexer.trivia()?;
+                    // a removed first entry drops its separator, leading trivia stays pending
+                    if kept || pending.kept_any {
+                        pending.bytes.extend(after);
+                        pending.bytes.push(b',');
+                        pending.bytes.extend(before_next);
+                    }
+                    pending.kept_any |= kept;
+                }
+                Some(byte) if byte == close => {
+                    pending.bytes.extend(after);
+                    break;
+                }
+                _ => {
+                    return Err(self
+                        .lexer
+                        .error(format!("expected ',' or '{}'", close as char)))
+                }
+            }
+        }
+        self.lexer.consume()?;
+        self.writer.write_all(&pending.bytes)?;
+        self.writer.write_all(&[close])?;
+        Ok(())
+    }
+
+    fn entry(
+        &mut self,
+        pending: &mut Pending,
+        keys: &mut HashSet<String>,
+        depth: usize,
+    ) -> Result<bool> {
+        self.lexer.capture();
+        let key = self.lexer.string()?;
+        if !keys.insert(key.clone()) {
+            return Err(self.lexer.duplicate_key(&key));
+        }
+        let mut head = self.lexer.take_captured();
+        head.extend(self.lexer.trivia()?);
+        self.lexer.expect(b':')?;
+        head.push(b':');
+        head.extend(self.lexer.trivia()?);
+
+        self.path.push(PathSegment::Key(key.clone()));
+        let kept = if self.storage.contains(&key) || self.storage.contains_path(&self.path) {
+            let path = self.path.clone();
+            let value = self.masked(|masker, value| masker.hide(value, &path), true, depth)?;
+            self.write_entry(pending, &head, value)?
+        } else {
+            self.walk(pending, &head, depth)?
+        };
+        self.path.pop();
+        Ok(kept)
+    }
+
+    fn item(&mut self, pending: &mut Pending, index: usize, depth: usize) -> Result<bool> {
+        self.path.push(PathSegment::Index(index));
+        let kept = if self.storage.contains_path(&self.path) {
+            let path = self.path.clone();
+            let value = self.masked(|masker, value| masker.hide(value, &path), true, depth)?;
+            self.write_entry(pending, &[], value)?
+        } else {
+            self.walk(pending, &[], depth)?
+        };
+        self.path.pop();
+        Ok(kept)
+    }
+
+    // value under a key that is not sensitive: containers are walked, scalars are inspected
+    fn walk(&mut self, pending: &mut Pending, head: &[u8], depth: usize) -> Result<bool> {
+        match self.lexer.peek()? {
+            Some(open @ (b'{' | b'[')) => {
+                self.flush(pending, head)?;
+                self.container(if open == b'{' { b'}' } else { b']' }, depth + 1)?;
+                Ok(true)
+            }
+            _ => {
+                let path = self.path.clone();
+                let value =
+                    self.masked(|masker, value| masker.inspect(value, &path), false, depth)?;
+                self.write_entry(pending, head, value)
+            }
+        }
+    }
+
+    fn write_entry(
+        &mut self,
+        pending: &mut Pending,
+        head: &[u8],
+        value: Option<Vec<u8>>,
+    ) -> Result<bool> {
+        let Some(value) = value else {
+            // separator before the removed entry goes away with it
+            if pending.kept_any {
+                pending.bytes.clear();
+            }
+            return Ok(false);
+        };
+        self.flush(pending, head)?;
+        self.writer.write_all(&value)?;
+        Ok(true)
+    }
+
+    fn flush(&mut self, pending: &mut Pending, head: &[u8]) -> Result<()> {
+        self.writer.write_all(&pending.bytes)?;
+        self.writer.write_all(head)?;
+        pending.bytes.clear();
+        Ok(())
+    }
+
+    // reads the next value and returns its new text, or the original one if it didn't change
+    fn masked(
+        &mut self,
+        transform: impl FnOnce(&mut T, &Value) -> Option<Value>,
+        whole: bool,
+        depth: usize,
+    ) -> Result<Option<Vec<u8>>> {
+        self.lexer.capture();
+        let value = match whole {
+            true => self.lexer.value(depth + 1),
+            false => self.lexer.scalar(),
+        };
+        let raw = self.lexer.take_captured();
+        let value = value?;
+        let masked = match transform(self.masker, &value) {
+            Some(masked) if masked == value => Some(raw),
+            Some(masked) => Some(serde_json::to_vec(&masked)?),
+            None => None,
+        };
+        Ok(masked)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::keys_storage::InMemoryKeysStorage;
+    use crate::mask::{MaskStrategy, Masker};
+    use std::collections::{HashMap, HashSet};
+
+    fn run(input: &str, keys: &[&str]) -> String {
+        let keys: HashSet<String> = keys.iter().map(|key| key.to_string()).collect();
+        let storage = InMemoryKeysStorage::init_with(&keys).unwrap();
+        let key_strategies = HashMap::from([(String::from("token"), MaskStrategy::Remove)]);
+        let mut masker = Masker::new(MaskStrategy::Placeholder, key_strategies).unwrap();
+        let mut output = Vec::new();
+        preserve(input.as_bytes(), &mut output, &storage, &mut masker).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_only_masked_values_change() {
+        let input = "{\n  // owner\n  \"zeta\":1.50e3,\n  \"alpha\" : {\"password\": \"secret\"},\n  \"big\": 12345678901234567890123\n}\n";
+        let expected = "{\n  // owner\n  \"zeta\":1.50e3,\n  \"alpha\" : {\"password\": \"String\"},\n  \"big\": 12345678901234567890123\n}\n";
+        assert_eq!(run(input, &["password"]), expected);
+    }
+
+    #[test]
+    fn test_masked_containers_are_compact() {
+        let input = "{\"password\": {\n  \"old\": \"a\",\n  \"new\": [1, true]\n}}";
+        let expected = "{\"password\": {\"old\":\"String\",\"new\":[\"Number\",\"Bool\"]}}";
+        assert_eq!(run(input, &["password"]), expected);
+    }
+
+    #[test]
+    fn test_removed_entries_take_separators() {
+        assert_eq!(
+            run("{\n  \"token\": 1,\n  \"a\": 2\n}", &["token"]),
+            "{\n  \"a\": 2\n}"
+        );
+        assert_eq!(
+            run("{\n  \"a\": 1,\n  \"token\": 2\n}", &["token"]),
+            "{\n  \"a\": 1\n}"
+        );
+        assert_eq!(
+            run("{\"a\": 1, \"token\": 2, \"b\": 3}", &["token"]),
+            "{\"a\": 1, \"b\": 3}"
+        );
+        assert_eq!(run("[{\"token\": 1}]", &["token"]), "[{}]");
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let storage = InMemoryKeysStorage::init_with(&HashSet::new()).unwrap();
+        let mut masker = Masker::new(MaskStrategy::Placeholder, HashMap::new()).unwrap();
+        let input = "{\"a\": 1, // again\n \"a\": 2}";
+        let result = preserve(input.as_bytes(), Vec::new(), &storage, &mut masker);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("duplicate key 'a'"));
+    }
+
+    #[test]
+    fn test_trailing_commas_and_paths() {
+        assert_eq!(run("[1, 2, 3,]", &["$[1]"]), "[1, \"Number\", 3,]");
+        assert_eq!(run(" 7 ", &["$"]), " \"Number\" ");
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::io::Write;
+
+const INDENT: &[u8] = b"  ";
+
+/// Writes JSON piece by piece in the same layout as `serde_json::to_string_pretty`.
+pub struct PrettyWriter<W> {
+    writer: W,
+    // for every open container, whether something was written into it already
+    containers: Vec<bool>,
+}
+
+impl<W: Write> PrettyWriter<W> {
+    pub fn new(writer: W) -> Self {
+        PrettyWriter {
+            writer,
+            containers: Vec::new(),
+        }
+    }
+
+    pub fn begin(&mut self, open: u8) -> Result<()> {
+        self.writer.write_all(&[open])?;
+        self.containers.push(false);
+        Ok(())
+    }
+
+    pub fn end(&mut self, close: u8) -> Result<()> {
+        if self.containers.pop() == Some(true) {
+            self.writer.write_all(b"\n")?;
+            self.indent()?;
+        }
+        self.writer.write_all(&[close])?;
+        Ok(())
+    }
+
+    /// Starts the next array item.
+    pub fn item(&mut self) -> Result<()> {
+        if let Some(has_items) = self.containers.last_mut() {
+            let separator: &[u8] = if *has_items { b",\n" } else { b"\n" };
+            *has_items = true;
+            self.writer.write_all(separator)?;
+            self.indent()?;
+        }
+        Ok(())
+    }
+
+    /// Starts the next object entry.
+    pub fn key(&mut self, key: &str) -> Result<()> {
+        self.item()?;
+        self.writer.write_all(&serde_json::to_vec(key)?)?;
+        self.writer.write_all(b": ")?;
+        Ok(())
+    }
+
+    pub fn value(&mut self, value: &Value) -> Result<()> {
+        match value {
+            Value::Array(array) => {
+                self.begin(b'[')?;
+                for item in array {
+                    self.item()?;
+                    self.value(item)?;
+                }
+                self.end(b']')
+            }
+            Value::Object(map) => {
+                self.begin(b'{')?;
+                for (key, value) in map {
+                    self.key(key)?;
+                    self.value(value)?;
+                }
+                self.end(b'}')
+            }
+            scalar => self.scalar(scalar),
+        }
+    }
+
+    // serialized separately, so write errors stay io::Error and a closed pipe can be noticed
+    fn scalar(&mut self, value: &Value) -> Result<()> {
+        self.writer.write_all(&serde_json::to_vec(value)?)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn indent(&mut self) -> Result<()> {
+        for _ in 0..self.containers.len() {
+            self.writer.write_all(INDENT)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_same_as_serde_json() {
+        let value = json!({
+            "a": [1, 2.5, {"b": null}],
+            "empty": {},
+            "list": [],
+            "text": "quote \" and \n"
+        });
+        let mut output = Vec::new();
+        let mut writer = PrettyWriter::new(&mut output);
+        writer.value(&value).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+    }
+}